@@ -0,0 +1,245 @@
+use std::cmp::Reverse;
+use serde_json::Value;
+
+use crate::character::Character;
+use crate::monster::Monster;
+
+/// A stage of a boss fight that starts once the boss drops to a share of its health
+#[derive(Debug, Clone)]
+pub struct Phase {
+    pub below: u8,              // Percent of the boss's starting health
+    pub attack: i32,            // Stat changes applied when the phase starts
+    pub defense: i32,
+    pub regen: i32,
+    pub summon: Vec<String>,    // Monster kinds from the map brought into the room
+    pub narration: String,
+}
+
+impl Phase {
+    pub fn from_json(phase: &Value) -> Option<Phase> {
+        Some(Phase {
+            below: phase["below"].as_u64()?.min(100) as u8,
+            attack: phase["attack"].as_i64().unwrap_or(0) as i32,
+            defense: phase["defense"].as_i64().unwrap_or(0) as i32,
+            regen: phase["regen"].as_i64().unwrap_or(0) as i32,
+            summon: match phase["summon"].as_array() {
+                Some(summon) => summon.iter().filter_map(|s| s.as_str()).map(|s| s.to_string()).collect(),
+                None => Vec::new(),
+            },
+            narration: phase["narration"].as_str().unwrap_or("").to_string(),
+        })
+    }
+}
+
+/// A special move the boss uses every few rounds on top of its normal attack
+#[derive(Debug, Clone)]
+pub struct Ability {
+    pub name: String,
+    pub cooldown: u32,  // Rounds to wait before using it again
+    pub damage: i16,    // Dealt to every player fighting, ignoring defense
+    pub heal: i16,      // Health the boss regains, up to its starting health
+    pub narration: String,
+}
+
+impl Ability {
+    pub fn from_json(ability: &Value) -> Option<Ability> {
+        let name = ability["name"].as_str()?.to_string();
+
+        Some(Ability {
+            cooldown: ability["cooldown"].as_u64().unwrap_or(1) as u32,
+            damage: ability["damage"].as_i64().unwrap_or(0) as i16,
+            heal: ability["heal"].as_i64().unwrap_or(0) as i16,
+            narration: ability["narration"].as_str().map(|n| n.to_string()).unwrap_or(format!("uses {}!", name)),
+            name,
+        })
+    }
+}
+
+/// Extra behaviour for a monster kind from the map's "bosses" list
+#[derive(Debug, Clone)]
+pub struct Boss {
+    pub monster: String,    // Monster kind this applies to
+    pub lockdown: bool,     // Nobody leaves the room mid-fight except by fleeing
+    pub phases: Vec<Phase>, // In the order they trigger
+    pub abilities: Vec<Ability>,
+}
+
+impl Boss {
+    pub fn from_json(boss: &Value) -> Option<Boss> {
+        let mut phases = match boss["phases"].as_array() {
+            Some(phases) => phases.iter().filter_map(Phase::from_json).collect::<Vec<Phase>>(),
+            None => Vec::new(),
+        };
+
+        phases.sort_by_key(|p| Reverse(p.below));
+
+        Some(Boss {
+            monster: boss["monster"].as_str()?.to_string(),
+            lockdown: boss["lockdown"].as_bool().unwrap_or(false),
+            phases,
+            abilities: match boss["abilities"].as_array() {
+                Some(abilities) => abilities.iter().filter_map(Ability::from_json).collect(),
+                None => Vec::new(),
+            },
+        })
+    }
+}
+
+/// Load the bosses from the map
+pub fn load_bosses(map: &Value) -> Vec<Boss> {
+    match map["bosses"].as_array() {
+        Some(bosses) => bosses.iter().filter_map(Boss::from_json).collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn find_boss<'a>(bosses: &'a [Boss], kind: &str) -> Option<&'a Boss> {
+    bosses.iter().find(|b| b.monster == kind)
+}
+
+/// What a boss did at the end of a round
+#[derive(Debug, Default)]
+pub struct BossTurn {
+    pub narration: Vec<String>,
+    pub damage: i16,            // To every player fighting
+    pub summon: Vec<String>,
+}
+
+/// A boss fight in progress, remembering the boss's stats from before it started
+#[derive(Debug, Clone)]
+pub struct Encounter {
+    pub monster: u32,   // Id of the boss monster
+    pub room: u16,
+    pub lockdown: bool,
+    phase: usize,       // Phases triggered so far
+    cooldowns: Vec<u32>,
+    attack: u16,
+    defense: u16,
+    regen: u16,
+    health: i16,
+}
+
+/// Every boss fight in progress
+#[derive(Debug, Default)]
+pub struct Encounters {
+    active: Vec<Encounter>,
+}
+
+impl Encounters {
+    pub fn new() -> Encounters {
+        Encounters { active: Vec::new() }
+    }
+
+    /// Start a fight against a boss if one isn't already going, remembering its stats to restore if it's abandoned
+    pub fn engage(&mut self, boss: &Boss, monster: &Monster) -> usize {
+        if let Some(index) = self.active.iter().position(|e| e.monster == monster.id) {
+            return index;
+        }
+
+        // Abilities start ready so the boss opens with them
+        self.active.push(Encounter {
+            monster: monster.id,
+            room: monster.current_room,
+            lockdown: boss.lockdown,
+            phase: 0,
+            cooldowns: vec![0; boss.abilities.len()],
+            attack: monster.attack,
+            defense: monster.defense,
+            regen: monster.regen,
+            health: monster.health,
+        });
+
+        self.active.len() - 1
+    }
+
+    /// Play the boss's part of a round: use abilities that are ready, then start any phases its health has fallen into
+    pub fn round(&mut self, boss: &Boss, monster: &mut Monster) -> BossTurn {
+        let index = self.engage(boss, monster);
+
+        let encounter = &mut self.active[index];
+        let mut turn = BossTurn::default();
+
+        if monster.health <= 0 {
+            return turn;
+        }
+
+        for (ability, cooldown) in boss.abilities.iter().zip(encounter.cooldowns.iter_mut()) {
+            if *cooldown > 0 {
+                *cooldown -= 1;
+                continue;
+            }
+
+            *cooldown = ability.cooldown;
+
+            turn.narration.push(format!("{} {}", monster.name, ability.narration));
+            turn.damage = turn.damage.saturating_add(ability.damage);
+
+            if ability.heal > 0 {
+                monster.health = monster.health.saturating_add(ability.heal).min(encounter.health.max(monster.health));
+            }
+        }
+
+        while let Some(phase) = boss.phases.get(encounter.phase) {
+            if monster.health as i32 * 100 > encounter.health as i32 * phase.below as i32 {
+                break;
+            }
+
+            encounter.phase += 1;
+
+            monster.attack = (monster.attack as i32 + phase.attack).clamp(0, u16::MAX as i32) as u16;
+            monster.defense = (monster.defense as i32 + phase.defense).clamp(0, u16::MAX as i32) as u16;
+            monster.regen = (monster.regen as i32 + phase.regen).clamp(0, u16::MAX as i32) as u16;
+
+            if !phase.narration.is_empty() {
+                turn.narration.push(phase.narration.clone());
+            }
+
+            turn.summon.extend(phase.summon.iter().cloned());
+        }
+
+        turn
+    }
+
+    /// The boss keeping a room sealed, if any
+    pub fn lockdown<'a>(&self, room: u16, monsters: &'a [Monster]) -> Option<&'a Monster> {
+        self.active.iter()
+            .filter(|e| e.room == room && e.lockdown)
+            .find_map(|e| monsters.iter().find(|m| m.id == e.monster && m.health > 0))
+    }
+
+    /// Forget the fight against a boss that has been killed, returning whether there was one
+    pub fn end(&mut self, monster: u32) -> bool {
+        let before = self.active.len();
+
+        self.active.retain(|e| e.monster != monster);
+
+        self.active.len() < before
+    }
+
+    /// Call off fights in rooms no living player is left in, putting the bosses back the way they were.
+    /// Returns the names of the bosses that recovered.
+    pub fn abandon(&mut self, characters: &[Character], monsters: &mut [Monster]) -> Vec<String> {
+        let mut recovered = Vec::new();
+
+        self.active.retain(|encounter| {
+            let watched = characters.iter().any(|c| c.active && c.health > 0 && c.current_room == encounter.room);
+
+            if watched {
+                return true;
+            }
+
+            if let Some(monster) = monsters.iter_mut().find(|m| m.id == encounter.monster && m.health > 0) {
+                monster.attack = encounter.attack;
+                monster.defense = encounter.defense;
+                monster.regen = encounter.regen;
+                monster.health = encounter.health;
+
+                recovered.push(monster.name.clone());
+            }
+
+            false
+        });
+
+        recovered
+    }
+}
@@ -1,6 +1,24 @@
 use std::fmt::{self,Display, Formatter};
 use std::sync::Arc;
 use std::net::TcpStream;
+use std::result;
+use std::time::Instant;
+
+use crate::combat::Fighter;
+use crate::item::{Item, ItemKind};
+use crate::quest::QuestProgress;
+
+/// Points a new character may spread across attack, defense, and regen
+pub const INITIAL_POINTS: u16 = 40;
+
+/// Hard limit on attack + defense + regen for any character, advertised in GAME
+pub const STAT_LIMIT: u16 = 500;
+
+/// Experience needed to gain each level
+pub const EXPERIENCE_PER_LEVEL: u32 = 100;
+
+/// Stat points granted for each level gained
+pub const POINTS_PER_LEVEL: u16 = 10;
 
 // Follow Character struct
 
@@ -17,6 +35,17 @@ pub struct Character {
     pub gold: u16,
     pub current_room: u16,
     pub description: String,
+    pub inventory: Vec<Item>,
+    pub weapon: Option<Item>,
+    pub armor: Option<Item>,
+    pub experience: u32,
+    pub level: u16,
+    pub stat_points: u16,
+    pub respawn_at: Option<Instant>, // When a dead character comes back to life
+    pub discovered: Vec<(u16, String)>, // Exits found with /search or opened by NPCs, by room number and destination
+    pub claimed: Vec<(String, String)>, // Dialogue rewards already received, by NPC and node
+    pub quests: Vec<QuestProgress>,
+    pub visited: Vec<u16>, // Rooms the character has been in, in the order they first got there
 }
 
 // initial points 40
@@ -34,20 +63,293 @@ impl Character {
             gold: 0,
             current_room: 0,
             description,
+            inventory: Vec::new(),
+            weapon: None,
+            armor: None,
+            experience: 0,
+            level: 1,
+            stat_points: 0,
+            respawn_at: None,
+            discovered: Vec::new(),
+            claimed: Vec::new(),
+            quests: Vec::new(),
+            visited: Vec::new(),
         }
     }
 
     pub fn update_room(&mut self, room: u16) {
         self.current_room = room;
+        self.visit(room);
+    }
+
+    /// Remember that the character has been in a room
+    pub fn visit(&mut self, room: u16) {
+        if !self.visited.contains(&room) {
+            self.visited.push(room);
+        }
+    }
+
+    pub fn visited(&self, room: u16) -> bool {
+        self.visited.contains(&room)
     }
 
     pub fn update_connection(&mut self, conn: Arc<TcpStream>) {
         self.conn = conn;
     }
+
+    /// This character's stats as seen by the combat rules
+    pub fn fighter(&self) -> Fighter {
+        Fighter {
+            name: self.name.clone(),
+            attack: self.attack,
+            defense: self.defense,
+            regen: self.regen,
+            health: self.health,
+            joins: (self.flags >> 6) & 1 == 1,
+        }
+    }
+
+    /// Whether other players may take this character's gold; only the dead can be looted, a living
+    /// character who logged out has their flags cleared but keeps their gold
+    pub fn lootable(&self) -> bool {
+        self.health <= 0
+    }
+
+    /// Combined attack, defense, and regen which may never exceed `STAT_LIMIT`
+    pub fn stat_total(&self) -> u32 {
+        self.attack as u32 + self.defense as u32 + self.regen as u32
+    }
+
+    /// Add experience and level up as many times as it allows, returning the number of levels gained
+    pub fn gain_experience(&mut self, experience: u32) -> u16 {
+        self.experience = self.experience.saturating_add(experience);
+
+        let level = (1 + self.experience / EXPERIENCE_PER_LEVEL).min(u16::MAX as u32) as u16;
+        let gained = level.saturating_sub(self.level);
+
+        self.level = level;
+        self.stat_points = self.stat_points.saturating_add(gained.saturating_mul(POINTS_PER_LEVEL));
+
+        gained
+    }
+
+    /// Raise stats to the requested values using earned stat points, returning the points spent
+    pub fn spend_points(&mut self, attack: u16, defense: u16, regen: u16) -> result::Result<u16, &'static str> {
+        if attack < self.attack || defense < self.defense || regen < self.regen {
+            return Err("Stats cannot be lowered once the game has started!");
+        }
+
+        let total = attack as u32 + defense as u32 + regen as u32;
+        let spent = total - self.stat_total();
+
+        if spent > self.stat_points as u32 {
+            return Err("Not enough stat points to raise those stats!");
+        }
+
+        if total > STAT_LIMIT as u32 {
+            return Err("Those stats would exceed the stat limit!");
+        }
+
+        self.attack = attack;
+        self.defense = defense;
+        self.regen = regen;
+        self.stat_points -= spent as u16;
+
+        Ok(spent as u16)
+    }
+
+    /// Add gold, stopping at the most a character can carry
+    pub fn add_gold(&mut self, amount: u16) {
+        self.gold = self.gold.saturating_add(amount);
+    }
+
+    /// Take gold if the character has enough, returning whether it was paid
+    pub fn spend_gold(&mut self, amount: u16) -> bool {
+        match self.gold.checked_sub(amount) {
+            Some(gold) => {
+                self.gold = gold;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Whether equipping or consuming the item keeps the character within `STAT_LIMIT`
+    pub fn within_stat_limit(&self, item: &Item) -> bool {
+        let replaced = match item.kind {
+            ItemKind::Weapon => self.weapon.as_ref(),
+            ItemKind::Armor => self.armor.as_ref(),
+            _ => None,
+        };
+
+        let removed = replaced.map_or(0, |r| r.attack as u32 + r.defense as u32 + r.regen as u32);
+        let added = item.attack as u32 + item.defense as u32 + item.regen as u32;
+
+        self.stat_total() - removed + added <= STAT_LIMIT as u32
+    }
+
+    /// Remove the first item with the given name from the inventory
+    pub fn take_item(&mut self, name: &str) -> Option<Item> {
+        let index = self.inventory.iter().position(|i| i.name.eq_ignore_ascii_case(name))?;
+
+        Some(self.inventory.remove(index))
+    }
+
+    /// Whether the character has found or been given the way through an exit from a room
+    pub fn discovered(&self, room_num: u16, exit: &str) -> bool {
+        self.discovered.iter().any(|(room, name)| *room == room_num && name == exit)
+    }
+
+    pub fn has_item(&self, name: &str) -> bool {
+        self.inventory.iter().any(|i| i.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Equip a weapon or armor and add its stats, returning whatever was in that slot to the inventory
+    pub fn equip(&mut self, item: Item) {
+        match item.kind {
+            ItemKind::Weapon => {
+                if let Some(old) = self.weapon.take() {
+                    self.remove_bonus(&old);
+                    self.inventory.push(old);
+                }
+
+                self.add_bonus(&item);
+                self.weapon = Some(item);
+            },
+            ItemKind::Armor => {
+                if let Some(old) = self.armor.take() {
+                    self.remove_bonus(&old);
+                    self.inventory.push(old);
+                }
+
+                self.add_bonus(&item);
+                self.armor = Some(item);
+            },
+            _ => self.inventory.push(item),
+        }
+    }
+
+    /// Unequip the named weapon or armor and move it back to the inventory
+    pub fn unequip(&mut self, name: &str) -> bool {
+        let slot = if self.weapon.as_ref().is_some_and(|w| w.name.eq_ignore_ascii_case(name)) {
+            &mut self.weapon
+        } else if self.armor.as_ref().is_some_and(|a| a.name.eq_ignore_ascii_case(name)) {
+            &mut self.armor
+        } else {
+            return false;
+        };
+
+        let item = slot.take().unwrap();
+
+        self.remove_bonus(&item);
+        self.inventory.push(item);
+
+        true
+    }
+
+    /// Apply a consumable's stats to the character permanently
+    pub fn consume(&mut self, item: &Item) {
+        self.add_bonus(item);
+        self.health = self.health.saturating_add(item.health);
+    }
+
+    fn add_bonus(&mut self, item: &Item) {
+        self.attack = self.attack.saturating_add(item.attack);
+        self.defense = self.defense.saturating_add(item.defense);
+        self.regen = self.regen.saturating_add(item.regen);
+    }
+
+    fn remove_bonus(&mut self, item: &Item) {
+        self.attack = self.attack.saturating_sub(item.attack);
+        self.defense = self.defense.saturating_sub(item.defense);
+        self.regen = self.regen.saturating_sub(item.regen);
+    }
 }
 
 impl Display for Character {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "\n\tName: {}\n\tFlags: {:#02x}\n\tAttack: {}\n\tDefense: {}\n\tRegen: {}\n\tHealth: {}\n\tGold: {}\n\tRoom: {}", self.name, self.flags, self.attack, self.defense, self.regen, self.health, self.gold, self.current_room)
+        write!(f, "\n\tName: {}\n\tFlags: {:#02x}\n\tAttack: {}\n\tDefense: {}\n\tRegen: {}\n\tHealth: {}\n\tGold: {}\n\tRoom: {}\n\tLevel: {}\n\tExperience: {}\n\tStat Points: {}", self.name, self.flags, self.attack, self.defense, self.regen, self.health, self.gold, self.current_room, self.level, self.experience, self.stat_points)
+    }
+}
+#[cfg(test)]
+pub mod tests {
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// A connection to nowhere in particular, for characters that never send anything
+    pub fn connection() -> Arc<TcpStream> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        Arc::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap())
     }
-}
\ No newline at end of file
+
+    /// A started character with the default stats
+    pub fn character(name: &str) -> Character {
+        let mut character = Character::new(connection(), name.to_string(), String::new());
+        character.flags = 0xd8;
+
+        character
+    }
+
+    #[test]
+    fn only_the_dead_can_be_looted() {
+        let mut alice = character("Alice");
+        assert!(!alice.lootable());
+
+        // Leaving clears the flags, including the alive bit
+        alice.active = false;
+        alice.flags = 0x00;
+        assert!(!alice.lootable());
+
+        alice.health = 0;
+        assert!(alice.lootable());
+    }
+
+    #[test]
+    fn spend_points_raises_stats() {
+        let mut alice = character("Alice");
+        alice.stat_points = 10;
+
+        assert_eq!(alice.spend_points(8, 12, 5), Ok(5));
+        assert_eq!((alice.attack, alice.defense, alice.regen, alice.stat_points), (8, 12, 5, 5));
+    }
+
+    #[test]
+    fn spend_points_cannot_lower_stats() {
+        let mut alice = character("Alice");
+        alice.stat_points = 10;
+
+        assert!(alice.spend_points(4, 15, 5).is_err());
+        assert_eq!((alice.attack, alice.defense, alice.stat_points), (5, 10, 10));
+    }
+
+    #[test]
+    fn spend_points_needs_enough_points() {
+        let mut alice = character("Alice");
+        alice.stat_points = 3;
+
+        assert!(alice.spend_points(9999, 10, 5).is_err());
+        assert!(alice.spend_points(9, 10, 5).is_err());
+        assert_eq!(alice.spend_points(8, 10, 5), Ok(3));
+    }
+
+    #[test]
+    fn spend_points_respects_stat_limit() {
+        let mut alice = character("Alice");
+        alice.stat_points = u16::MAX;
+
+        assert!(alice.spend_points(STAT_LIMIT, 10, 5).is_err());
+        assert!(alice.spend_points(STAT_LIMIT - 15, 10, 5).is_ok());
+        assert_eq!(alice.stat_total(), STAT_LIMIT as u32);
+    }
+
+    #[test]
+    fn experience_grants_levels_and_points() {
+        let mut alice = character("Alice");
+
+        assert_eq!(alice.gain_experience(EXPERIENCE_PER_LEVEL * 2 + 1), 2);
+        assert_eq!((alice.level, alice.stat_points), (3, POINTS_PER_LEVEL * 2));
+    }
+}
@@ -8,7 +8,7 @@ use std::sync::mpsc::SyncSender;
 
 use crate::message::Message;
 use crate::error_code::ErrorCode;
-use crate::character::Character;
+use crate::character::{Character, INITIAL_POINTS, STAT_LIMIT};
 
 type Result<T> = result::Result<T, ()>;
 
@@ -19,8 +19,8 @@ pub fn handle_client(stream: Arc<TcpStream>, messages: SyncSender<Message>, map_
     let mut buffer: Vec<u8> = Vec::new();
 
     // Server Constants
-    let initial_points: u16 = 40;
-    let stat_limit: u16 = 500;
+    let initial_points: u16 = INITIAL_POINTS;
+    let stat_limit: u16 = STAT_LIMIT;
 
     // Game Constants
     let mut started = false;
@@ -419,7 +419,8 @@ pub fn handle_client(stream: Arc<TcpStream>, messages: SyncSender<Message>, map_
                     }
                 }
 
-                let temp_name = String::from_utf8_lossy(&name_bytes).to_string();
+                let mut temp_name = String::from_utf8_lossy(&name_bytes).to_string();
+                if temp_name.is_empty() { temp_name = "Default".to_string() };
                 let temp_flags = metadata[32];
                 let temp_attack = u32::from_le_bytes([metadata[33], metadata[34], 0, 0]);
                 let temp_defense = u32::from_le_bytes([metadata[35], metadata[36], 0, 0]);
@@ -436,8 +437,9 @@ pub fn handle_client(stream: Arc<TcpStream>, messages: SyncSender<Message>, map_
                 // Calculate the total points
                 let total_points = temp_attack + temp_defense + temp_regen;
  
-                // Send Error if total points exceeds initial points
-                if total_points > initial_points as u32 {
+                // Send Error if total points exceeds initial points; a started character re-submitting itself is
+                // spending earned stat points instead, which the server checks
+                if (!started || temp_name != player.name) && total_points > initial_points as u32 {
                     eprintln!("[CLIENT]\tError: Total points exceeds initial points");
 
                     character_message = Message::Error {
@@ -455,8 +457,27 @@ pub fn handle_client(stream: Arc<TcpStream>, messages: SyncSender<Message>, map_
                     continue;
                 }
 
+                // Once started, the connection keeps its character, the server refuses any other
+                if started && temp_name != player.name {
+                    eprintln!("[CLIENT]\tError: Character already started, refusing new character: {}", temp_name);
+
+                    character_message = Message::Error {
+                        author: stream.clone(),
+                        message_type: 7,
+                        error: ErrorCode::PlayerExists,
+                        message_len: 36,
+                        message: b"You are already playing a character!".to_vec()
+                    };
+
+                    messages.send(character_message).map_err(|_err| {
+                        eprintln!("[CLIENT]\tError: Could not send error message to server");
+                    })?;
+
+                    continue;
+                }
+
                 // Set the character's stats
-                if temp_name != "" { player.name = temp_name } else { player.name = "Default".to_string() };
+                player.name = temp_name;
                 if temp_flags == 0x0 || temp_flags == 0xff {player.flags = 0xc8 } else { player.flags = temp_flags }; // 11001000 = 0xc8 (ready, not started) 11011000 = 0xd8 (ready, started)
                 player.attack = temp_attack as u16;
                 player.defense = temp_defense as u16;
@@ -538,23 +559,28 @@ pub fn handle_client(stream: Arc<TcpStream>, messages: SyncSender<Message>, map_
                 break;
             },
             14 => {
-                // Client tried to send a version message, but we don't care about it
-                eprintln!("[CLIENT]\tError: Client tried to send a version message. Ignoring.");
-                let _ = stream.as_ref().shutdown(std::net::Shutdown::Read);
+                // The client is telling us which extensions it understands
+                let mut metadata = [0u8; 4];
 
-                character_message = Message::Error {
-                    author: stream.clone(),
-                    message_type: 7,
-                    error: ErrorCode::Other,
-                    message_len: 32,
-                    message: b"Sorry no time traveling allowed!".to_vec()
-                };
+                reader.read_exact(&mut metadata).map_err(|_err| {
+                    eprintln!("[CLIENT]\tError: Unable to obtain metadata of message 14, assuming character disconnected");
+                })?;
 
-                messages.send(character_message).map_err(|_err| {
-                    eprintln!("[CLIENT]\tError: Could not send error message to server");
+                let extension_len = u16::from_le_bytes([metadata[2], metadata[3]]);
+                let mut extensions = vec![0u8; extension_len as usize];
+
+                reader.read_exact(&mut extensions).map_err(|_err| {
+                    eprintln!("[CLIENT]\tError: Could not read from stream, assuming character disconnected");
                 })?;
 
-                break;
+                character_message = Message::Version {
+                    author: stream.clone(),
+                    message_type: 14,
+                    major_rev: metadata[0],
+                    minor_rev: metadata[1],
+                    extension_len,
+                    extensions
+                };
             },
             _ => {
                 eprintln!("[CLIENT]\tError: Unknown message type: {}", message_type[0]);
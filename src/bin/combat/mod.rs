@@ -0,0 +1,317 @@
+use rand::{Rng, RngCore};
+
+/// A player or monster's stats as seen by the combat rules
+#[derive(Debug, Clone)]
+pub struct Fighter {
+    pub name: String,
+    pub attack: u16,
+    pub defense: u16,
+    pub regen: u16,
+    pub health: i16,
+    pub joins: bool, // Join battle flag
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Players,
+    Monsters,
+}
+
+/// Something that happened to the fighter at `target` on the given side
+#[derive(Debug, Clone, PartialEq)]
+pub enum CombatEvent {
+    Hit { side: Side, target: usize, damage: i16 },
+    Blocked { side: Side, target: usize },
+    Missed { side: Side, target: usize },
+    Regen { side: Side, target: usize, amount: i16 },
+    Death { side: Side, target: usize },
+}
+
+/// The formulas a fight is resolved with; select one per map with the "combat" key
+pub trait CombatRules {
+    fn name(&self) -> &'static str;
+
+    /// Roll for turn order, the side with the highest roll attacks first (players win ties)
+    fn initiative(&self, fighter: &Fighter, rng: &mut dyn RngCore) -> i64;
+
+    /// Chance from 0.0 to 1.0 that the attacker lands a blow on the defender
+    fn hit_chance(&self, attacker: &Fighter, defender: &Fighter) -> f64;
+
+    /// Damage dealt to the defender by everyone who hit it this turn
+    fn damage(&self, attackers: &[&Fighter], defender: &Fighter, rng: &mut dyn RngCore) -> i64;
+
+    /// Health regained by a fighter after being hit
+    fn regen(&self, fighter: &Fighter) -> i16;
+
+    fn is_dead(&self, fighter: &Fighter) -> bool {
+        fighter.health <= 0
+    }
+}
+
+/// The original rules: every attack is pooled, defense is subtracted, and 10% of regen is restored
+pub struct Classic;
+
+impl CombatRules for Classic {
+    fn name(&self) -> &'static str {
+        "classic"
+    }
+
+    fn initiative(&self, _fighter: &Fighter, _rng: &mut dyn RngCore) -> i64 {
+        0
+    }
+
+    fn hit_chance(&self, _attacker: &Fighter, _defender: &Fighter) -> f64 {
+        1.0
+    }
+
+    fn damage(&self, attackers: &[&Fighter], defender: &Fighter, _rng: &mut dyn RngCore) -> i64 {
+        attackers.iter().map(|a| a.attack as i64).sum::<i64>() - defender.defense as i64
+    }
+
+    fn regen(&self, fighter: &Fighter) -> i16 {
+        (fighter.regen as f64 * 0.10) as i16
+    }
+}
+
+/// Each fighter strikes on their own: blows can miss, and defense halves each hit instead of the pool
+pub struct Skirmish;
+
+impl CombatRules for Skirmish {
+    fn name(&self) -> &'static str {
+        "skirmish"
+    }
+
+    fn initiative(&self, fighter: &Fighter, rng: &mut dyn RngCore) -> i64 {
+        rng.gen_range(1..=20) + fighter.regen as i64 / 10
+    }
+
+    fn hit_chance(&self, attacker: &Fighter, defender: &Fighter) -> f64 {
+        let total = attacker.attack as f64 + defender.defense as f64;
+
+        if total == 0.0 {
+            return 0.5;
+        }
+
+        (attacker.attack as f64 / total).clamp(0.2, 0.95)
+    }
+
+    fn damage(&self, attackers: &[&Fighter], defender: &Fighter, rng: &mut dyn RngCore) -> i64 {
+        attackers.iter()
+            .map(|a| (a.attack as i64 - defender.defense as i64 / 2).max(1) + rng.gen_range(0..=a.attack as i64 / 4))
+            .sum()
+    }
+
+    fn regen(&self, fighter: &Fighter) -> i16 {
+        (fighter.regen as f64 * 0.10) as i16
+    }
+}
+
+/// A slower grind: defense scales damage down rather than cancelling it, and regen is much stronger
+pub struct Attrition;
+
+impl CombatRules for Attrition {
+    fn name(&self) -> &'static str {
+        "attrition"
+    }
+
+    fn initiative(&self, fighter: &Fighter, _rng: &mut dyn RngCore) -> i64 {
+        fighter.defense as i64
+    }
+
+    fn hit_chance(&self, _attacker: &Fighter, _defender: &Fighter) -> f64 {
+        1.0
+    }
+
+    fn damage(&self, attackers: &[&Fighter], defender: &Fighter, _rng: &mut dyn RngCore) -> i64 {
+        let pool = attackers.iter().map(|a| a.attack as i64).sum::<i64>();
+
+        (pool * 100 / (100 + defender.defense as i64)).max(1)
+    }
+
+    fn regen(&self, fighter: &Fighter) -> i16 {
+        (fighter.regen / 4) as i16
+    }
+}
+
+/// Look up combat rules by the name used in the map, falling back to the classic rules
+pub fn combat_rules(name: &str) -> Box<dyn CombatRules> {
+    match name.to_lowercase().as_str() {
+        "classic" => Box::new(Classic),
+        "skirmish" => Box::new(Skirmish),
+        "attrition" => Box::new(Attrition),
+        _ => {
+            eprintln!("[COMBAT]\tError: Unknown combat rules '{}', using classic", name);
+            Box::new(Classic)
+        }
+    }
+}
+
+/// Play out one round of a fight between the players and monsters, updating their health in place
+pub fn resolve(rules: &dyn CombatRules, players: &mut [Fighter], monsters: &mut [Fighter], rng: &mut dyn RngCore) -> Vec<CombatEvent> {
+    let mut events = Vec::new();
+
+    let player_initiative = side_initiative(rules, players, rng);
+    let monster_initiative = side_initiative(rules, monsters, rng);
+
+    let order = if monster_initiative > player_initiative {
+        [Side::Monsters, Side::Players]
+    } else {
+        [Side::Players, Side::Monsters]
+    };
+
+    for attacking in order {
+        let (attackers, defenders, defending) = match attacking {
+            Side::Players => (&*players, &mut *monsters, Side::Monsters),
+            Side::Monsters => (&*monsters, &mut *players, Side::Players),
+        };
+
+        // Fighters killed earlier in the round don't get to attack
+        let attackers = attackers.iter()
+            .filter(|a| a.joins && !rules.is_dead(a))
+            .cloned()
+            .collect::<Vec<Fighter>>();
+
+        if attackers.is_empty() {
+            continue;
+        }
+
+        for (target, defender) in defenders.iter_mut().enumerate() {
+            if !defender.joins || rules.is_dead(defender) {
+                continue;
+            }
+
+            let hitters = attackers.iter()
+                .filter(|a| rng.gen::<f64>() < rules.hit_chance(a, defender))
+                .collect::<Vec<&Fighter>>();
+
+            if hitters.is_empty() {
+                events.push(CombatEvent::Missed { side: defending, target });
+                continue;
+            }
+
+            let damage = rules.damage(&hitters, defender, rng).clamp(0, i16::MAX as i64) as i16;
+
+            if damage == 0 {
+                events.push(CombatEvent::Blocked { side: defending, target });
+                continue;
+            }
+
+            defender.health = defender.health.saturating_sub(damage);
+            events.push(CombatEvent::Hit { side: defending, target, damage });
+
+            let regen = rules.regen(defender);
+
+            defender.health = defender.health.saturating_add(regen);
+            events.push(CombatEvent::Regen { side: defending, target, amount: regen });
+
+            if rules.is_dead(defender) {
+                events.push(CombatEvent::Death { side: defending, target });
+            }
+        }
+    }
+
+    events
+}
+
+fn side_initiative(rules: &dyn CombatRules, fighters: &[Fighter], rng: &mut dyn RngCore) -> i64 {
+    fighters.iter()
+        .filter(|f| f.joins && !rules.is_dead(f))
+        .map(|f| rules.initiative(f, rng))
+        .max()
+        .unwrap_or(i64::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn fighter(name: &str, attack: u16, defense: u16, regen: u16, health: i16) -> Fighter {
+        Fighter { name: name.to_string(), attack, defense, regen, health, joins: true }
+    }
+
+    #[test]
+    fn classic_pools_attack_against_defense() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut players = [fighter("Alice", 30, 10, 0, 100), fighter("Bob", 20, 10, 0, 100)];
+        let mut monsters = [fighter("Octorok", 5, 15, 50, 60)];
+
+        let events = resolve(&Classic, &mut players, &mut monsters, &mut rng);
+
+        // 30 + 20 - 15 = 35 damage, then 10% of 50 regen comes back
+        assert_eq!(events[0], CombatEvent::Hit { side: Side::Monsters, target: 0, damage: 35 });
+        assert_eq!(events[1], CombatEvent::Regen { side: Side::Monsters, target: 0, amount: 5 });
+        assert_eq!(monsters[0].health, 30);
+
+        // The monster's 5 attack is below either player's defense
+        assert!(events.contains(&CombatEvent::Blocked { side: Side::Players, target: 0 }));
+        assert!(events.contains(&CombatEvent::Blocked { side: Side::Players, target: 1 }));
+        assert_eq!(players[0].health, 100);
+    }
+
+    #[test]
+    fn dead_fighters_do_not_strike_back() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut players = [fighter("Alice", 100, 0, 0, 10)];
+        let mut monsters = [fighter("Keese", 50, 0, 0, 20)];
+
+        let events = resolve(&Classic, &mut players, &mut monsters, &mut rng);
+
+        assert!(events.contains(&CombatEvent::Death { side: Side::Monsters, target: 0 }));
+        assert!(!events.iter().any(|e| matches!(e, CombatEvent::Hit { side: Side::Players, .. })));
+        assert_eq!(players[0].health, 10);
+    }
+
+    #[test]
+    fn fighters_who_do_not_join_are_left_alone() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut players = [fighter("Alice", 50, 0, 0, 10), Fighter { joins: false, ..fighter("Bob", 50, 0, 0, 10) }];
+        let mut monsters = [fighter("Keese", 5, 0, 0, 200)];
+
+        let events = resolve(&Classic, &mut players, &mut monsters, &mut rng);
+
+        assert_eq!(monsters[0].health, 150);
+        assert!(!events.iter().any(|e| matches!(e, CombatEvent::Hit { side: Side::Players, target: 1, .. })));
+        assert_eq!(players[1].health, 10);
+    }
+
+    #[test]
+    fn attrition_scales_damage_and_always_hurts() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let attacker = fighter("Alice", 100, 0, 0, 10);
+        let defender = fighter("Darknut", 0, 100, 40, 10);
+
+        assert_eq!(Attrition.damage(&[&attacker], &defender, &mut rng), 50);
+        assert_eq!(Attrition.damage(&[&fighter("Bob", 0, 0, 0, 10)], &defender, &mut rng), 1);
+        assert_eq!(Attrition.regen(&defender), 10);
+    }
+
+    #[test]
+    fn skirmish_hit_chance_is_bounded() {
+        let weak = fighter("Alice", 1, 0, 0, 10);
+        let strong = fighter("Ganon", 1000, 1000, 0, 10);
+
+        assert_eq!(Skirmish.hit_chance(&weak, &strong), 0.2);
+        assert_eq!(Skirmish.hit_chance(&strong, &weak), 0.95);
+        assert_eq!(Skirmish.hit_chance(&fighter("A", 0, 0, 0, 1), &fighter("B", 0, 0, 0, 1)), 0.5);
+    }
+
+    #[test]
+    fn skirmish_damage_is_at_least_one_per_hit() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let attacker = fighter("Alice", 2, 0, 0, 10);
+        let defender = fighter("Darknut", 0, 100, 0, 10);
+
+        for _ in 0..20 {
+            assert_eq!(Skirmish.damage(&[&attacker], &defender, &mut rng), 1);
+        }
+    }
+
+    #[test]
+    fn unknown_rules_fall_back_to_classic() {
+        assert_eq!(combat_rules("Skirmish").name(), "skirmish");
+        assert_eq!(combat_rules("nonsense").name(), "classic");
+    }
+}
@@ -0,0 +1,55 @@
+use std::env;
+use std::fmt::{self, Debug, Formatter};
+
+/// Game rules the server operator can switch on or off in the .env file
+#[derive(Clone)]
+pub struct Config {
+    pub player_loot: bool,      // PLAYER_LOOT: players may loot the gold of dead players, off unless set
+    pub admin_token: Option<String>, // ADMIN_TOKEN: secret given with admin commands, which are refused when it is unset
+    pub stats_path: String,     // STATS_PATH: where player statistics are saved between runs
+    pub stats_export: String,   // STATS_EXPORT: where /export writes the statistics as CSV
+}
+
+impl Config {
+    pub fn from_env() -> Config {
+        Config {
+            player_loot: env_flag("PLAYER_LOOT", false),
+            admin_token: env::var("ADMIN_TOKEN").ok().filter(|token| !token.trim().is_empty()),
+            stats_path: env::var("STATS_PATH").unwrap_or(String::from("stats.json")),
+            stats_export: env::var("STATS_EXPORT").unwrap_or(String::from("stats.csv")),
+        }
+    }
+
+    /// Whether the token given with an admin command is the server's admin token; any client can claim any
+    /// character name, so names alone never grant admin rights
+    pub fn is_admin(&self, token: &str) -> bool {
+        self.admin_token.as_deref().is_some_and(|admin| admin == token)
+    }
+}
+
+// Written by hand so the admin token never ends up in the server log
+impl Debug for Config {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("player_loot", &self.player_loot)
+            .field("admin_token", &self.admin_token.as_ref().map(|_| "<set>"))
+            .field("stats_path", &self.stats_path)
+            .field("stats_export", &self.stats_export)
+            .finish()
+    }
+}
+
+/// Read a true/false environment variable, falling back to the default when unset or invalid
+fn env_flag(name: &str, default: bool) -> bool {
+    match env::var(name) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => {
+                eprintln!("[CONFIG]\tError: {} should be true or false, using {}", name, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,151 @@
+use std::time::{Duration, Instant};
+use serde_json::Value;
+
+use crate::character::Character;
+use crate::utilities::move_character;
+
+/// How players die and come back, read from the map's "death" section
+#[derive(Debug, Clone)]
+pub struct DeathRules {
+    pub respawn_room: u16,
+    pub respawn_delay: Duration,
+    pub respawn_health: i16,
+    pub gold_penalty: u8,   // Percent of the character's gold lost when they respawn
+    pub permadeath: bool,   // Dead characters never respawn and their name can be used again
+    pub narration: String,  // Sent to the player when they respawn
+}
+
+impl DeathRules {
+    pub fn from_map(map: &Value) -> DeathRules {
+        let death = &map["death"];
+
+        DeathRules {
+            respawn_room: death["respawn_room"].as_u64().unwrap_or(0) as u16,
+            respawn_delay: Duration::from_secs(death["respawn_delay"].as_u64().unwrap_or(10)),
+            respawn_health: death["respawn_health"].as_i64().unwrap_or(20) as i16,
+            gold_penalty: death["gold_penalty"].as_u64().unwrap_or(0).min(100) as u8,
+            permadeath: death["permadeath"].as_bool().unwrap_or(false),
+            narration: death["narration"].as_str().unwrap_or("You have been brought back to life.").to_string(),
+        }
+    }
+
+    /// Start the respawn timer for a character that was just killed, returning what to tell them
+    pub fn on_death(&self, character: &mut Character) -> String {
+        if self.permadeath {
+            character.respawn_at = None;
+
+            return String::from("You have died. Your adventure is over.");
+        }
+
+        character.respawn_at = Some(Instant::now() + self.respawn_delay);
+
+        format!("You have died. You will return in {} second(s).", self.respawn_delay.as_secs())
+    }
+
+    /// Whether a character is dead for good, so their name can start a new character
+    pub fn frees_name(&self, character: &Character) -> bool {
+        self.permadeath && !character.active && character.health <= 0
+    }
+
+    /// Gold lost on respawning with the given gold, rounded down
+    pub fn penalty(&self, gold: u16) -> u16 {
+        (gold as u32 * self.gold_penalty as u32 / 100) as u16
+    }
+
+    /// Whether a dead character's respawn timer has run out
+    pub fn respawn_due(&self, character: &Character) -> bool {
+        character.health <= 0 && character.respawn_at.is_some_and(|at| at <= Instant::now())
+    }
+
+    /// Bring a character back to life in the respawn room, returning the gold they lost
+    pub fn respawn(&self, map: &mut Value, character: &mut Character) -> u16 {
+        let old_room = character.current_room as usize;
+
+        move_character(map, character, self.respawn_room as usize, old_room);
+        character.update_room(self.respawn_room);
+
+        let penalty = self.penalty(character.gold);
+
+        character.spend_gold(penalty);
+        character.health = self.respawn_health;
+        character.respawn_at = None;
+
+        // Inactive characters keep their flags cleared until they reconnect
+        if character.active {
+            character.flags = 0x98 | (character.flags & 0x40); // 0xd8 = 11011000 when joining battles
+        }
+
+        penalty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::character::tests::character;
+
+    fn rules(death: Value) -> DeathRules {
+        DeathRules::from_map(&json!({ "death": death }))
+    }
+
+    #[test]
+    fn respawns_after_the_delay() {
+        let rules = rules(json!({ "respawn_delay": 5 }));
+        let mut alice = character("Alice");
+        alice.health = 0;
+
+        assert_eq!(rules.on_death(&mut alice), "You have died. You will return in 5 second(s).");
+        assert!(!rules.respawn_due(&alice));
+
+        alice.respawn_at = Some(Instant::now());
+        assert!(rules.respawn_due(&alice));
+
+        alice.health = 10;
+        assert!(!rules.respawn_due(&alice));
+    }
+
+    #[test]
+    fn penalty_rounds_down() {
+        assert_eq!(rules(json!({})).penalty(500), 0);
+        assert_eq!(rules(json!({ "gold_penalty": 33 })).penalty(10), 3);
+        assert_eq!(rules(json!({ "gold_penalty": 50 })).penalty(1), 0);
+        assert_eq!(rules(json!({ "gold_penalty": 100 })).penalty(u16::MAX), u16::MAX);
+        assert_eq!(rules(json!({ "gold_penalty": 250 })).penalty(40), 40);
+    }
+
+    #[test]
+    fn respawn_moves_and_charges_the_character() {
+        let rules = rules(json!({ "respawn_room": 0, "respawn_health": 15, "gold_penalty": 25 }));
+        let mut map = json!({ "rooms": [{ "characters": [] }, { "characters": ["Alice"] }] });
+
+        let mut alice = character("Alice");
+        alice.update_room(1);
+        alice.health = -4;
+        alice.gold = 10;
+        alice.respawn_at = Some(Instant::now());
+
+        assert_eq!(rules.respawn(&mut map, &mut alice), 2);
+        assert_eq!((alice.current_room, alice.health, alice.gold), (0, 15, 8));
+        assert!(alice.respawn_at.is_none());
+        assert_eq!(map["rooms"][0]["characters"], json!(["Alice"]));
+        assert_eq!(map["rooms"][1]["characters"], json!([]));
+    }
+
+    #[test]
+    fn permadeath_frees_the_name() {
+        let rules = rules(json!({ "permadeath": true }));
+        let mut alice = character("Alice");
+        alice.health = 0;
+
+        assert_eq!(rules.on_death(&mut alice), "You have died. Your adventure is over.");
+        assert!(alice.respawn_at.is_none());
+
+        // Only once they have logged out
+        assert!(!rules.frees_name(&alice));
+        alice.active = false;
+        assert!(rules.frees_name(&alice));
+
+        assert!(!DeathRules::from_map(&json!({})).frees_name(&alice));
+    }
+}
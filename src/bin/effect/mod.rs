@@ -0,0 +1,212 @@
+use rand::{Rng, RngCore};
+use serde_json::Value;
+
+use crate::character::Character;
+
+/// Something a room does to the characters in it, from the room's "effects" list in the map
+#[derive(Debug, Clone)]
+pub enum RoomEffect {
+    Damage { amount: i16, every: u64, narration: String },          // Hurts on entry and every `every` ticks
+    Heal { amount: i16, limit: i16, every: u64, narration: String }, // Heals on entry and every `every` ticks, up to `limit`
+    Darkness { light: Option<String> },                              // Hides everyone else in the room unless carrying the light
+    Teleport { room: u16, chance: u8, narration: String },           // Sends characters elsewhere as they walk in
+}
+
+impl RoomEffect {
+    pub fn from_json(effect: &Value) -> Option<RoomEffect> {
+        let narration = effect["narration"].as_str().unwrap_or("").to_string();
+        let every = effect["every"].as_u64().unwrap_or(1).max(1);
+
+        match effect["type"].as_str()? {
+            "damage" => Some(RoomEffect::Damage {
+                amount: effect["amount"].as_i64()? as i16,
+                every,
+                narration,
+            }),
+            "heal" => Some(RoomEffect::Heal {
+                amount: effect["amount"].as_i64()? as i16,
+                limit: effect["limit"].as_i64().unwrap_or(100) as i16,
+                every,
+                narration,
+            }),
+            "darkness" => Some(RoomEffect::Darkness { light: effect["light"].as_str().map(|l| l.to_string()) }),
+            "teleport" => Some(RoomEffect::Teleport {
+                room: effect["room"].as_u64()? as u16,
+                chance: effect["chance"].as_u64().unwrap_or(100).min(100) as u8,
+                narration,
+            }),
+            other => {
+                eprintln!("[EFFECT]\tError: Unknown room effect type '{}'", other);
+                None
+            }
+        }
+    }
+
+    /// Hurt or heal the character, returning what to tell them if anything happened
+    fn apply(&self, character: &mut Character) -> Option<String> {
+        match self {
+            RoomEffect::Damage { amount, narration, .. } => {
+                character.health = character.health.saturating_sub(*amount);
+                Some(narration.clone())
+            },
+            RoomEffect::Heal { amount, limit, narration, .. } if character.health < *limit => {
+                character.health = character.health.saturating_add(*amount).min(*limit);
+                Some(narration.clone())
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Every effect listed for a room in the map
+pub fn room_effects(map: &Value, room_num: usize) -> Vec<RoomEffect> {
+    match map["rooms"][room_num]["effects"].as_array() {
+        Some(effects) => effects.iter().filter_map(RoomEffect::from_json).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Whether the room is too dark for the character to see anyone else in it
+pub fn is_dark(map: &Value, room_num: usize, character: &Character) -> bool {
+    room_effects(map, room_num).iter().any(|effect| match effect {
+        RoomEffect::Darkness { light: Some(light) } => !character.has_item(light),
+        RoomEffect::Darkness { light: None } => true,
+        _ => false,
+    })
+}
+
+/// What a room did to a character
+#[derive(Debug, Default)]
+pub struct EffectResult {
+    pub narration: Vec<String>,
+    pub teleport: Option<(u16, String)>, // Where a trap sends the character and what to tell them if they go
+}
+
+/// Apply a room's effects to a character who just walked in
+pub fn enter_room(map: &Value, room_num: usize, character: &mut Character, rng: &mut dyn RngCore) -> EffectResult {
+    let mut result = EffectResult::default();
+
+    for effect in room_effects(map, room_num) {
+        if let RoomEffect::Teleport { room, chance, narration } = &effect {
+            if result.teleport.is_none() && rng.gen_range(0..100) < *chance {
+                result.teleport = Some((*room, narration.clone()));
+            }
+
+            continue;
+        }
+
+        result.narration.extend(effect.apply(character));
+    }
+
+    result.narration.retain(|n| !n.is_empty());
+
+    result
+}
+
+/// Apply the effects that repeat over time to a character standing in the room, returning what to tell them
+pub fn tick_room(map: &Value, room_num: usize, character: &mut Character, tick: u64) -> Vec<String> {
+    room_effects(map, room_num).iter()
+        .filter(|effect| match effect {
+            RoomEffect::Damage { every, .. } | RoomEffect::Heal { every, .. } => tick.is_multiple_of(*every),
+            _ => false,
+        })
+        .filter_map(|effect| effect.apply(character))
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::character::tests::character;
+    use crate::item::Item;
+
+    fn map() -> Value {
+        json!({ "rooms": [
+            { "id": 0, "name": "Bog", "effects": [
+                { "type": "damage", "amount": 5, "every": 2, "narration": "The bog burns!" },
+                { "type": "heal", "amount": 3, "narration": "" },
+            ] },
+            { "id": 1, "name": "Spring", "effects": [{ "type": "heal", "amount": 10, "limit": 25, "every": 3, "narration": "You feel better." }] },
+            { "id": 2, "name": "Cave", "effects": [{ "type": "darkness", "light": "Torch" }] },
+            { "id": 3, "name": "Void", "effects": [{ "type": "darkness" }, { "type": "fog" }] },
+            { "id": 4, "name": "Trap", "effects": [
+                { "type": "teleport", "room": 1, "chance": 0, "narration": "Never" },
+                { "type": "teleport", "room": 2, "narration": "Whoosh!" },
+                { "type": "teleport", "room": 3 },
+            ] },
+        ] })
+    }
+
+    #[test]
+    fn entering_applies_damage_and_healing() {
+        let map = map();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut alice = character("Alice");
+
+        // Healing with no narration is applied without telling anyone
+        let result = enter_room(&map, 0, &mut alice, &mut rng);
+        assert_eq!(result.narration, vec!["The bog burns!"]);
+        assert_eq!(alice.health, 18);
+        assert!(result.teleport.is_none());
+
+        let result = enter_room(&map, 1, &mut alice, &mut rng);
+        assert_eq!(result.narration, vec!["You feel better."]);
+        assert_eq!(alice.health, 25);
+
+        // Already at the limit, nothing to heal
+        assert!(enter_room(&map, 1, &mut alice, &mut rng).narration.is_empty());
+    }
+
+    #[test]
+    fn ticks_repeat_effects_on_their_schedule() {
+        let map = map();
+        let mut alice = character("Alice");
+
+        assert!(tick_room(&map, 0, &mut alice, 1).is_empty());
+        assert_eq!(alice.health, 23);
+
+        assert_eq!(tick_room(&map, 0, &mut alice, 2), vec!["The bog burns!"]);
+        assert_eq!(alice.health, 21);
+
+        alice.health = 20;
+        assert!(tick_room(&map, 1, &mut alice, 2).is_empty());
+        assert_eq!(tick_room(&map, 1, &mut alice, 3), vec!["You feel better."]);
+        assert_eq!(alice.health, 25);
+        assert!(tick_room(&map, 2, &mut alice, 6).is_empty());
+    }
+
+    #[test]
+    fn darkness_hides_the_room_without_a_light() {
+        let map = map();
+        let mut alice = character("Alice");
+
+        assert!(!is_dark(&map, 0, &alice));
+        assert!(is_dark(&map, 2, &alice));
+        assert!(is_dark(&map, 3, &alice));
+
+        alice.inventory.push(Item::from_json(&json!({ "name": "Torch", "kind": "key" })).unwrap());
+
+        assert!(!is_dark(&map, 2, &alice));
+        assert!(is_dark(&map, 3, &alice));
+    }
+
+    #[test]
+    fn the_first_trap_that_springs_wins() {
+        let map = map();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut alice = character("Alice");
+
+        for _ in 0..20 {
+            let result = enter_room(&map, 4, &mut alice, &mut rng);
+
+            assert_eq!(result.teleport, Some((2, String::from("Whoosh!"))));
+            assert!(result.narration.is_empty());
+        }
+
+        assert_eq!(room_effects(&map, 3).len(), 1);
+    }
+}
@@ -0,0 +1,246 @@
+use serde_json::Value;
+
+use crate::item::{place_item, room_items};
+use crate::monster::{spawn_monster, Monster};
+
+/// Something that happens in the world on its own, from the map's "events" list
+#[derive(Debug, Clone)]
+pub enum WorldEvent {
+    // Switches between day and night, swapping in each listed room's night description
+    DayNight { day: String, night: String, rooms: Vec<(usize, String)> },
+    // Brings monsters into a room until `count` of them are alive there
+    Invasion { monster: String, room: usize, count: usize, narration: String },
+    // Leaves an item on the floor of a room if one isn't there already
+    Treasure { item: String, room: usize, narration: String },
+}
+
+impl WorldEvent {
+    pub fn from_json(event: &Value) -> Option<WorldEvent> {
+        let narration = event["narration"].as_str().unwrap_or("").to_string();
+
+        match event["type"].as_str()? {
+            "day_night" => Some(WorldEvent::DayNight {
+                day: event["day"].as_str().unwrap_or("The sun rises.").to_string(),
+                night: event["night"].as_str().unwrap_or("Night falls.").to_string(),
+                rooms: match event["rooms"].as_array() {
+                    Some(rooms) => rooms.iter().filter_map(|r| {
+                        Some((r["room"].as_u64()? as usize, r["night_description"].as_str()?.to_string()))
+                    }).collect(),
+                    None => Vec::new(),
+                },
+            }),
+            "invasion" => Some(WorldEvent::Invasion {
+                monster: event["monster"].as_str()?.to_string(),
+                room: event["room"].as_u64()? as usize,
+                count: event["count"].as_u64().unwrap_or(1) as usize,
+                narration,
+            }),
+            "treasure" => Some(WorldEvent::Treasure {
+                item: event["item"].as_str()?.to_string(),
+                room: event["room"].as_u64()? as usize,
+                narration,
+            }),
+            other => {
+                eprintln!("[EVENT]\t\tError: Unknown event type '{}'", other);
+                None
+            }
+        }
+    }
+}
+
+/// What an event did, for the server to tell the players about
+#[derive(Debug, Default)]
+pub struct EventOutcome {
+    pub narration: String,
+    pub everyone: bool,     // Tell every player rather than only those in `rooms`
+    pub rooms: Vec<usize>,  // Rooms that changed and should be sent again to the players in them
+}
+
+/// Runs the map's events every so many ticks
+#[derive(Debug)]
+pub struct Scheduler {
+    events: Vec<(WorldEvent, u64)>,     // Each event with how many ticks apart it runs
+    night: bool,
+    day_descriptions: Vec<(usize, String)>,
+}
+
+impl Scheduler {
+    pub fn new(map: &Value) -> Scheduler {
+        let events = match map["events"].as_array() {
+            Some(events) => events.iter().filter_map(|event| {
+                let every = event["every"].as_u64().unwrap_or(60).max(1);
+
+                Some((WorldEvent::from_json(event)?, every))
+            }).collect(),
+            None => Vec::new(),
+        };
+
+        Scheduler { events, night: false, day_descriptions: Vec::new() }
+    }
+
+    /// How many events are scheduled
+    pub fn count(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.night
+    }
+
+    /// Run every event due on this tick
+    pub fn run(&mut self, tick: u64, map: &mut Value, monsters: &mut Vec<Monster>) -> Vec<EventOutcome> {
+        let due = self.events.iter()
+            .filter(|(_, every)| tick.is_multiple_of(*every))
+            .map(|(event, _)| event.clone())
+            .collect::<Vec<WorldEvent>>();
+
+        due.iter().filter_map(|event| self.apply(event, map, monsters)).collect()
+    }
+
+    fn apply(&mut self, event: &WorldEvent, map: &mut Value, monsters: &mut Vec<Monster>) -> Option<EventOutcome> {
+        match event {
+            WorldEvent::DayNight { day, night, rooms } => {
+                self.night = !self.night;
+
+                if self.night {
+                    self.day_descriptions = rooms.iter()
+                        .map(|(room, _)| (*room, map["rooms"][*room]["description"].as_str().unwrap_or("").to_string()))
+                        .collect();
+
+                    for (room, description) in rooms.iter() {
+                        map["rooms"][*room]["description"] = Value::String(description.clone());
+                    }
+                } else {
+                    for (room, description) in self.day_descriptions.drain(..) {
+                        map["rooms"][room]["description"] = Value::String(description);
+                    }
+                }
+
+                Some(EventOutcome {
+                    narration: if self.night { night.clone() } else { day.clone() },
+                    everyone: true,
+                    rooms: rooms.iter().map(|(room, _)| *room).collect(),
+                })
+            },
+            WorldEvent::Invasion { monster, room, count, narration } => {
+                let alive = monsters.iter().filter(|m| m.kind == *monster && m.current_room as usize == *room && m.health > 0).count();
+
+                if alive >= *count {
+                    return None;
+                }
+
+                for _ in alive..*count {
+                    let name = spawn_monster(map, monsters, monster, *room)?;
+
+                    println!("[EVENT]\t\t{} invaded room {}", name, room);
+                }
+
+                Some(EventOutcome { narration: narration.clone(), everyone: true, rooms: vec![*room] })
+            },
+            WorldEvent::Treasure { item, room, narration } => {
+                if room_items(map, *room).iter().any(|i| i == item) {
+                    return None;
+                }
+
+                place_item(map, *room, item);
+
+                println!("[EVENT]\t\t{} appeared in room {}", item, room);
+
+                Some(EventOutcome { narration: narration.clone(), everyone: false, rooms: vec![*room] })
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn map(events: Value) -> Value {
+        json!({
+            "rooms": [
+                { "id": 0, "name": "Square", "description": "A sunny square.", "items": [], "monsters": [] },
+                { "id": 1, "name": "Gate", "description": "The town gate.", "monsters": [] },
+            ],
+            "monsters": [{ "name": "Rat", "health": 5, "attack": 1 }],
+            "events": events,
+        })
+    }
+
+    #[test]
+    fn night_gives_way_to_the_same_day() {
+        let mut map = map(json!([{ "type": "day_night", "every": 2, "night": "Dark.", "rooms": [
+            { "room": 0, "night_description": "A moonlit square." },
+        ] }]));
+        let mut scheduler = Scheduler::new(&map);
+
+        assert!(scheduler.run(1, &mut map, &mut Vec::new()).is_empty());
+
+        let outcomes = scheduler.run(2, &mut map, &mut Vec::new());
+
+        assert!(scheduler.is_night());
+        assert_eq!(outcomes[0].narration, "Dark.");
+        assert_eq!(outcomes[0].rooms, vec![0]);
+        assert_eq!(map["rooms"][0]["description"], "A moonlit square.");
+        assert_eq!(map["rooms"][1]["description"], "The town gate.");
+
+        let outcomes = scheduler.run(4, &mut map, &mut Vec::new());
+
+        assert!(!scheduler.is_night());
+        assert_eq!(outcomes[0].narration, "The sun rises.");
+        assert_eq!(map["rooms"][0]["description"], "A sunny square.");
+
+        // A second night starts from the restored description, not the night one
+        scheduler.run(6, &mut map, &mut Vec::new());
+        scheduler.run(8, &mut map, &mut Vec::new());
+
+        assert_eq!(map["rooms"][0]["description"], "A sunny square.");
+    }
+
+    #[test]
+    fn invasions_top_up_to_the_count() {
+        let mut map = map(json!([{ "type": "invasion", "monster": "Rat", "room": 1, "count": 3, "every": 1, "narration": "Rats!" }]));
+        let mut scheduler = Scheduler::new(&map);
+        let mut monsters = Vec::new();
+
+        let outcomes = scheduler.run(1, &mut map, &mut monsters);
+
+        assert_eq!(outcomes[0].narration, "Rats!");
+        assert_eq!(monsters.len(), 3);
+        assert_eq!(map["rooms"][1]["monsters"].as_array().unwrap().len(), 3);
+
+        // Already at the count, so nothing happens
+        assert!(scheduler.run(2, &mut map, &mut monsters).is_empty());
+        assert_eq!(monsters.len(), 3);
+
+        // Only the fallen one is replaced, and its body is cleared away
+        monsters[0].health = 0;
+        scheduler.run(3, &mut map, &mut monsters);
+
+        assert_eq!(monsters.len(), 3);
+        assert!(monsters.iter().all(|m| m.health > 0 && m.current_room == 1));
+        assert_eq!(map["rooms"][1]["monsters"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn treasure_is_not_left_twice() {
+        let mut map = map(json!([{ "type": "treasure", "item": "Gem", "room": 0, "every": 1, "narration": "Something glints." }]));
+        let mut scheduler = Scheduler::new(&map);
+
+        let outcomes = scheduler.run(1, &mut map, &mut Vec::new());
+
+        assert_eq!(outcomes[0].narration, "Something glints.");
+        assert!(!outcomes[0].everyone);
+        assert_eq!(room_items(&map, 0), vec!["Gem"]);
+
+        assert!(scheduler.run(2, &mut map, &mut Vec::new()).is_empty());
+        assert_eq!(room_items(&map, 0), vec!["Gem"]);
+
+        // Once picked up it can turn up again
+        map["rooms"][0]["items"] = json!([]);
+        scheduler.run(3, &mut map, &mut Vec::new());
+
+        assert_eq!(room_items(&map, 0), vec!["Gem"]);
+    }
+}
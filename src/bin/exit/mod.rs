@@ -0,0 +1,230 @@
+use std::fmt::{self,Display, Formatter};
+use serde_json::Value;
+
+use crate::character::Character;
+use crate::monster::Monster;
+
+/// A way out of a room; in the map either a plain room name or an object with conditions
+#[derive(Debug, Clone)]
+pub struct Exit {
+    pub room: String,                   // Name of the room the exit leads to
+    pub requires_item: Option<String>,  // Item the character must be carrying, unless an NPC opened the way
+    pub toll: u16,                      // Gold paid every time the exit is used
+    pub requires_dead: Option<String>,  // Monster in this room that must be dead first
+    pub one_way: bool,                  // The destination's exit back here can't be used
+    pub hidden: bool,                   // Not shown or usable until found with /search or shown by an NPC
+    pub portal: Option<String>,         // Name of the zone the exit leads into, set when the zones are loaded
+}
+
+impl Exit {
+    pub fn from_json(exit: &Value) -> Option<Exit> {
+        if let Some(room) = exit.as_str() {
+            return Some(Exit {
+                room: room.to_string(),
+                requires_item: None,
+                toll: 0,
+                requires_dead: None,
+                one_way: false,
+                hidden: false,
+                portal: None,
+            });
+        }
+
+        Some(Exit {
+            room: exit["room"].as_str()?.to_string(),
+            requires_item: exit["requires_item"].as_str().map(|i| i.to_string()),
+            toll: exit["toll"].as_u64().unwrap_or(0) as u16,
+            requires_dead: exit["requires_dead"].as_str().map(|m| m.to_string()),
+            one_way: exit["one_way"].as_bool().unwrap_or(false),
+            hidden: exit["hidden"].as_bool().unwrap_or(false),
+            portal: exit["portal"].as_str().map(|z| z.to_string()),
+        })
+    }
+
+    /// Whether the character may pass, with the reason why not
+    pub fn check(&self, character: &Character, monsters: &[Monster]) -> Result<(), String> {
+        if let Some(item) = &self.requires_item {
+            if !character.has_item(item) && !character.discovered(character.current_room, &self.room) {
+                return Err(format!("The way to {} is locked, you need a {}!", self.room, item));
+            }
+        }
+
+        if let Some(kind) = &self.requires_dead {
+            let guarded = monsters.iter().any(|m| {
+                m.current_room == character.current_room && (m.kind == *kind || m.name == *kind) && m.health > 0
+            });
+
+            if guarded {
+                return Err(format!("{} blocks the way to {}!", kind, self.room));
+            }
+        }
+
+        if character.gold < self.toll {
+            return Err(format!("It costs {} gold to go to {}, you only have {}!", self.toll, self.room, character.gold));
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Exit {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.room)?;
+
+        if let Some(item) = &self.requires_item {
+            write!(f, " (needs {})", item)?;
+        }
+
+        if self.toll > 0 {
+            write!(f, " ({} gold toll)", self.toll)?;
+        }
+
+        if let Some(monster) = &self.requires_dead {
+            write!(f, " (guarded by {})", monster)?;
+        }
+
+        if let Some(zone) = &self.portal {
+            write!(f, " (portal to {})", zone)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Every exit listed for a room in the map
+pub fn room_exits(map: &Value, room_num: usize) -> Vec<Exit> {
+    match map["rooms"][room_num]["exits"].as_array() {
+        Some(exits) => exits.iter().filter_map(Exit::from_json).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The exits a character can see from a room, leaving out undiscovered hidden exits and the wrong way through one-way exits
+pub fn visible_exits(map: &Value, room_num: usize, character: &Character) -> Vec<Exit> {
+    let here = map["rooms"][room_num]["name"].as_str().unwrap_or("");
+
+    room_exits(map, room_num).into_iter()
+        .filter(|exit| !exit.hidden || character.discovered(room_num as u16, &exit.room))
+        .filter(|exit| {
+            let back = match room_id(map, &exit.room) {
+                Some(id) => room_exits(map, id as usize),
+                None => return false,
+            };
+
+            !back.iter().any(|b| b.room == here && b.one_way)
+        })
+        .collect()
+}
+
+/// Whether the map hides what lies behind exits to rooms a character hasn't been to yet
+pub fn fog_of_war(map: &Value) -> bool {
+    map["fog_of_war"].as_bool().unwrap_or(false)
+}
+
+/// Look up a room's number by its name
+pub fn room_id(map: &Value, name: &str) -> Option<u16> {
+    map["rooms"].as_array()?
+        .iter()
+        .find(|r| r["name"].as_str() == Some(name))
+        .and_then(|r| r["id"].as_u64())
+        .map(|id| id as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::character::tests::character;
+    use crate::item::Item;
+
+    fn map() -> Value {
+        json!({ "rooms": [
+            { "id": 0, "name": "Glade", "exits": ["Cave", { "room": "Cellar", "hidden": true }, { "room": "Cliff" }] },
+            { "id": 1, "name": "Cave", "exits": ["Glade"] },
+            { "id": 2, "name": "Cellar", "exits": ["Glade"] },
+            { "id": 3, "name": "Cliff", "exits": [{ "room": "Glade", "one_way": true }] },
+        ] })
+    }
+
+    fn exit(value: Value) -> Exit {
+        Exit::from_json(&value).unwrap()
+    }
+
+    #[test]
+    fn from_json_reads_names_and_objects() {
+        let plain = exit(json!("Cave"));
+        assert_eq!(plain.room, "Cave");
+        assert!(plain.requires_item.is_none() && plain.toll == 0 && !plain.hidden && !plain.one_way);
+
+        let gated = exit(json!({ "room": "Vault", "requires_item": "Key", "toll": 5, "requires_dead": "Ogre", "one_way": true, "hidden": true }));
+        assert_eq!(gated.requires_item.as_deref(), Some("Key"));
+        assert_eq!(gated.toll, 5);
+        assert_eq!(gated.requires_dead.as_deref(), Some("Ogre"));
+        assert!(gated.one_way && gated.hidden);
+        assert_eq!(gated.to_string(), "Vault (needs Key) (5 gold toll) (guarded by Ogre)");
+
+        assert!(Exit::from_json(&json!({ "toll": 5 })).is_none());
+    }
+
+    #[test]
+    fn check_locks() {
+        let locked = exit(json!({ "room": "Vault", "requires_item": "Key" }));
+        let mut alice = character("Alice");
+
+        assert!(locked.check(&alice, &[]).is_err());
+
+        alice.discovered.push((0, String::from("Vault")));
+        assert!(locked.check(&alice, &[]).is_ok());
+
+        let mut bob = character("Bob");
+        bob.inventory.push(Item::from_json(&json!({ "name": "key", "kind": "key" })).unwrap());
+        assert!(locked.check(&bob, &[]).is_ok());
+    }
+
+    #[test]
+    fn check_guards() {
+        let guarded = exit(json!({ "room": "Vault", "requires_dead": "Ogre" }));
+        let alice = character("Alice");
+
+        let mut ogre = Monster::new(String::from("Ogre"), String::new());
+        ogre.health = 10;
+
+        assert!(guarded.check(&alice, std::slice::from_ref(&ogre)).is_err());
+
+        ogre.current_room = 1;
+        assert!(guarded.check(&alice, std::slice::from_ref(&ogre)).is_ok());
+
+        ogre.current_room = 0;
+        ogre.health = 0;
+        assert!(guarded.check(&alice, &[ogre]).is_ok());
+    }
+
+    #[test]
+    fn check_tolls() {
+        let toll = exit(json!({ "room": "Bridge", "toll": 5 }));
+        let mut alice = character("Alice");
+
+        alice.gold = 4;
+        assert!(toll.check(&alice, &[]).is_err());
+
+        alice.gold = 5;
+        assert!(toll.check(&alice, &[]).is_ok());
+    }
+
+    #[test]
+    fn visible_exits_hide_secrets_and_one_way_returns() {
+        let map = map();
+        let mut alice = character("Alice");
+
+        let names = |exits: Vec<Exit>| exits.into_iter().map(|e| e.room).collect::<Vec<String>>();
+
+        // The Cliff only leads back here one way, so it can't be reached from the Glade
+        assert_eq!(names(visible_exits(&map, 0, &alice)), vec!["Cave"]);
+
+        alice.discovered.push((0, String::from("Cellar")));
+        assert_eq!(names(visible_exits(&map, 0, &alice)), vec!["Cave", "Cellar"]);
+
+        assert_eq!(names(visible_exits(&map, 3, &alice)), vec!["Glade"]);
+        assert_eq!(room_exits(&map, 0).len(), 3);
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::{self,Display, Formatter};
+use serde_json::Value;
+
+/// Name of the VERSION extension for clients that accept ITEM messages
+pub const ITEM_EXTENSION: &str = "ITEM";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Weapon = 0,     // 0
+    Armor = 1,      // 1
+    Consumable = 2, // 2
+    Key = 3,        // 3
+}
+
+impl ItemKind {
+    pub fn parse(kind: &str) -> Option<ItemKind> {
+        match kind.to_lowercase().as_str() {
+            "weapon" => Some(ItemKind::Weapon),
+            "armor" => Some(ItemKind::Armor),
+            "consumable" => Some(ItemKind::Consumable),
+            "key" => Some(ItemKind::Key),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ItemKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ItemKind::Weapon => write!(f, "Weapon"),
+            ItemKind::Armor => write!(f, "Armor"),
+            ItemKind::Consumable => write!(f, "Consumable"),
+            ItemKind::Key => write!(f, "Key"),
+        }
+    }
+}
+
+// Weapons and armor add their stats while equipped, consumables apply them once when used
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub name: String,
+    pub kind: ItemKind,
+    pub attack: u16,
+    pub defense: u16,
+    pub regen: u16,
+    pub health: i16,
+    pub value: u16,
+    pub description: String,
+}
+
+impl Item {
+    /// Build an item from its entry in the map's "items" list
+    pub fn from_json(item: &Value) -> Option<Item> {
+        let name = item["name"].as_str()?.to_string();
+
+        let kind = match ItemKind::parse(item["kind"].as_str().unwrap_or("")) {
+            Some(kind) => kind,
+            None => {
+                eprintln!("[ITEM]\t\tError: Item '{}' has an unknown kind", name);
+                return None;
+            }
+        };
+
+        Some(Item {
+            name,
+            kind,
+            attack: item["attack"].as_u64().unwrap_or(0) as u16,
+            defense: item["defense"].as_u64().unwrap_or(0) as u16,
+            regen: item["regen"].as_u64().unwrap_or(0) as u16,
+            health: item["health"].as_i64().unwrap_or(0) as i16,
+            value: item["value"].as_u64().unwrap_or(0) as u16,
+            description: item["description"].as_str().unwrap_or("").to_string(),
+        })
+    }
+
+    pub fn equippable(&self) -> bool {
+        self.kind == ItemKind::Weapon || self.kind == ItemKind::Armor
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.kind)?;
+
+        let bonuses = [("Attack", self.attack as i32), ("Defense", self.defense as i32), ("Regen", self.regen as i32), ("Health", self.health as i32)]
+            .iter()
+            .filter(|(_, value)| *value != 0)
+            .map(|(stat, value)| format!("{:+} {}", value, stat))
+            .collect::<Vec<String>>();
+
+        if !bonuses.is_empty() {
+            write!(f, " [{}]", bonuses.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Load the item catalog from the map
+pub fn load_items(map: &Value) -> Vec<Item> {
+    match map["items"].as_array() {
+        Some(items) => items.iter().filter_map(Item::from_json).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Find an item in the catalog by name
+pub fn find_item(items: &[Item], name: &str) -> Option<Item> {
+    items.iter().find(|i| i.name.eq_ignore_ascii_case(name)).cloned()
+}
+
+/// Names of the items lying on the floor of the given room
+pub fn room_items(map: &Value, room_num: usize) -> Vec<String> {
+    match map["rooms"][room_num]["items"].as_array() {
+        Some(items) => items.iter().filter_map(|i| i.as_str()).map(|i| i.to_string()).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Leave an item on the floor of the given room
+pub fn place_item(map: &mut Value, room_num: usize, name: &str) {
+    let room = &mut map["rooms"][room_num];
+
+    if !room["items"].is_array() {
+        room["items"] = Value::Array(Vec::new());
+    }
+
+    if let Some(items) = room["items"].as_array_mut() {
+        items.push(Value::String(name.to_string()));
+    }
+}
+
+/// Pick an item up off the floor of the given room, returning its name as written in the map
+pub fn remove_room_item(map: &mut Value, room_num: usize, name: &str) -> Option<String> {
+    let items = map["rooms"][room_num]["items"].as_array_mut()?;
+    let index = items.iter().position(|i| i.as_str().is_some_and(|i| i.eq_ignore_ascii_case(name)))?;
+
+    items.remove(index).as_str().map(|i| i.to_string())
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+/// Most messages that can be waiting for a single character
+pub const MAILBOX_LIMIT: usize = 50;
+
+/// A message waiting for an offline character
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub sender: String,
+    pub recipient: String,
+    pub message: String,
+}
+
+/// Messages held for offline characters until they reconnect
+#[derive(Debug, Default)]
+pub struct Mailbox {
+    pending: HashMap<String, Vec<Mail>>,
+}
+
+impl Mailbox {
+    pub fn new() -> Mailbox {
+        Mailbox { pending: HashMap::new() }
+    }
+
+    /// Queue a message for the recipient; returns false if their mailbox is full
+    pub fn post(&mut self, mail: Mail) -> bool {
+        let queue = self.pending.entry(mail.recipient.clone()).or_default();
+
+        if queue.len() >= MAILBOX_LIMIT {
+            return false;
+        }
+
+        queue.push(mail);
+
+        true
+    }
+
+    /// Remove and return every message waiting for the given character
+    pub fn take(&mut self, recipient: &str) -> Vec<Mail> {
+        self.pending.remove(recipient).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mail(recipient: &str, message: &str) -> Mail {
+        Mail { sender: String::from("Alice"), recipient: recipient.to_string(), message: message.to_string() }
+    }
+
+    #[test]
+    fn post_refuses_full_mailboxes() {
+        let mut mailbox = Mailbox::new();
+
+        for number in 0..MAILBOX_LIMIT {
+            assert!(mailbox.post(mail("Bob", &number.to_string())));
+        }
+
+        assert!(!mailbox.post(mail("Bob", "one too many")));
+        assert!(mailbox.post(mail("Carol", "still room")));
+        assert_eq!(mailbox.take("Bob").len(), MAILBOX_LIMIT);
+    }
+
+    #[test]
+    fn take_drains_in_order() {
+        let mut mailbox = Mailbox::new();
+
+        mailbox.post(mail("Bob", "first"));
+        mailbox.post(mail("Bob", "second"));
+        mailbox.post(mail("Carol", "other"));
+
+        let messages = mailbox.take("Bob").into_iter().map(|m| m.message).collect::<Vec<String>>();
+
+        assert_eq!(messages, vec!["first", "second"]);
+        assert!(mailbox.take("Bob").is_empty());
+        assert_eq!(mailbox.take("Carol").len(), 1);
+        assert!(mailbox.take("Dave").is_empty());
+    }
+}
@@ -344,7 +344,7 @@ impl Display for Message {
                 write!(f, "Start {{ author: {:?}, message_type: {} }}", author, message_type)
             },
             Message::Error { author, message_type, error, message_len, message } => {
-                write!(f, "Error {{ author: {:?}, message_type: {}, error: {}, message_len: {}, message: {} }}", author, message_type, error, message_len, String::from_utf8_lossy(&message))
+                write!(f, "Error {{ author: {:?}, message_type: {}, error: {}, message_len: {}, message: {} }}", author, message_type, error, message_len, String::from_utf8_lossy(message))
             },
             Message::Accept { author, message_type, accept_type } => {
                 write!(f, "Accept {{ author: {:?}, message_type: {}, accept_type: {} }}", author, message_type, accept_type)
@@ -1,8 +1,13 @@
 use std::fmt::{self,Display, Formatter};
+use serde_json::Value;
+
+use crate::combat::Fighter;
 
 #[derive(Debug, Clone)]
 pub struct Monster {
-    pub name: String,
+    pub id: u32,        // Unique for every monster spawned while the server runs
+    pub kind: String,   // Name of the monster in the map, shared by every spawn of it
+    pub name: String,   // Unique display name, the kind with a number when the map spawns it more than once
     pub flags: u8,
     pub attack: u16,
     pub defense: u16,
@@ -11,12 +16,17 @@ pub struct Monster {
     pub gold: u16,
     pub current_room: u16,
     pub description: String,
+    pub drops: Vec<String>,
+    pub experience: u32,
+    pub spawned: bool,  // Brought in by an event or a boss rather than placed by the map, cleared away once dead
 }
 
 // initial points 40
 impl Monster {
     pub fn new(name: String, description: String) -> Monster {
         Monster {
+            id: 0,
+            kind: name.clone(),
             name,
             flags: 0xF8,
             attack: 5,
@@ -26,6 +36,47 @@ impl Monster {
             gold: 0,
             current_room: 0,
             description,
+            drops: Vec::new(),
+            experience: 20,
+            spawned: false,
+        }
+    }
+
+    /// Build a monster from its entry in the map's "monsters" list
+    pub fn from_json(monster: &Value, id: u32) -> Monster {
+        Monster {
+            id,
+            kind: monster["name"].as_str().unwrap_or("ERROR").to_string(),
+            name: monster["name"].as_str().unwrap_or("ERROR").to_string(),
+            description: monster["description"].as_str().unwrap_or("SOMETHING WENT WRONG").to_string(),
+            flags: 0xF8,
+            attack: monster["attack"].as_u64().unwrap_or(0) as u16,
+            defense: monster["defense"].as_u64().unwrap_or(0) as u16,
+            regen: monster["regen"].as_u64().unwrap_or(0) as u16,
+            health: monster["health"].as_u64().unwrap_or(0) as i16,
+            gold: monster["gold"].as_u64().unwrap_or(0) as u16,
+            current_room: monster["current_room"].as_u64().unwrap_or(0) as u16,
+            drops: match monster["drops"].as_array() {
+                Some(drops) => drops.iter().filter_map(|d| d.as_str()).map(|d| d.to_string()).collect(),
+                None => Vec::new()
+            },
+            // Default to the monster's combined stats when the map doesn't say
+            experience: monster["experience"].as_u64().unwrap_or(
+                monster["attack"].as_u64().unwrap_or(0) + monster["defense"].as_u64().unwrap_or(0) + monster["regen"].as_u64().unwrap_or(0)
+            ) as u32,
+            spawned: false,
+        }
+    }
+
+    /// This monster's stats as seen by the combat rules
+    pub fn fighter(&self) -> Fighter {
+        Fighter {
+            name: self.name.clone(),
+            attack: self.attack,
+            defense: self.defense,
+            regen: self.regen,
+            health: self.health,
+            joins: (self.flags >> 6) & 1 == 1,
         }
     }
 }
@@ -34,4 +85,171 @@ impl Display for Monster {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "\n\tName: {}\n\tFlags: {:#02x}\n\tAttack: {}\n\tDefense: {}\n\tRegen: {}\n\tHealth: {}\n\tGold: {}\n\tRoom: {}", self.name, self.flags, self.attack, self.defense, self.regen, self.health, self.gold, self.current_room)
     }
-}
\ No newline at end of file
+}
+/// Spawn every monster in the map as its own instance and list them in their rooms by display name
+pub fn load_monsters(map: &mut Value) -> Vec<Monster> {
+    let spawns = match map["monsters"].as_array() {
+        Some(spawns) => spawns.clone(),
+        None => {
+            eprintln!("[MONSTER]\tError: Map has no monsters list");
+            return Vec::new();
+        }
+    };
+
+    let mut monsters: Vec<Monster> = spawns.iter().enumerate().map(|(id, monster)| Monster::from_json(monster, id as u32 + 1)).collect();
+
+    // Number the spawns of any monster the map places more than once so each can be targeted
+    for index in 0..monsters.len() {
+        let kind = monsters[index].kind.clone();
+        let count = monsters.iter().filter(|m| m.kind == kind).count();
+
+        if count > 1 {
+            let number = monsters[..index].iter().filter(|m| m.kind == kind).count() + 1;
+            let suffix = format!(" #{}", number);
+
+            // Names are sent in 32 bytes, so make room for the number
+            let mut name = kind.clone();
+
+            while name.len() + suffix.len() > 32 {
+                name.pop();
+            }
+
+            monsters[index].name = format!("{}{}", name, suffix);
+        }
+    }
+
+    // Rooms list their monsters by display name
+    if let Some(rooms) = map["rooms"].as_array_mut() {
+        for (room_num, room) in rooms.iter_mut().enumerate() {
+            room["monsters"] = Value::Array(
+                monsters.iter()
+                    .filter(|m| m.current_room as usize == room_num)
+                    .map(|m| Value::String(m.name.clone()))
+                    .collect()
+            );
+        }
+    }
+
+    monsters
+}
+
+/// Find a monster by display name, only looking in the given room
+pub fn find_room_monster<'a>(monsters: &'a mut [Monster], room_num: usize, name: &str) -> Option<&'a mut Monster> {
+    monsters.iter_mut().find(|m| m.current_room as usize == room_num && m.name == name)
+}
+
+/// The number a monster of this kind is shown with, 1 for a kind the map only places once
+fn spawn_number(monster: &Monster) -> usize {
+    monster.name.rsplit_once(" #")
+        .and_then(|(_, number)| number.parse::<usize>().ok())
+        .unwrap_or(1)
+}
+
+/// Bring a new monster of the given kind into a room, copying its stats from the map, and return its display name.
+/// Spawned monsters that have died in the room are cleared away first so repeated spawns don't pile up.
+pub fn spawn_monster(map: &mut Value, monsters: &mut Vec<Monster>, kind: &str, room_num: usize) -> Option<String> {
+    let template = map["monsters"].as_array()?.iter().find(|m| m["name"].as_str() == Some(kind))?.clone();
+
+    let dead = monsters.iter()
+        .filter(|m| m.spawned && m.health <= 0 && m.current_room as usize == room_num)
+        .map(|m| Value::String(m.name.clone()))
+        .collect::<Vec<Value>>();
+
+    monsters.retain(|m| !(m.spawned && m.health <= 0 && m.current_room as usize == room_num));
+
+    if let Some(list) = map["rooms"][room_num]["monsters"].as_array_mut() {
+        list.retain(|name| !dead.contains(name));
+    }
+
+    let id = monsters.iter().map(|m| m.id).max().unwrap_or(0) + 1;
+    let mut monster = Monster::from_json(&template, id);
+
+    // Numbered after the highest of its kind so a name is never shared, even once some are gone
+    let number = monsters.iter().filter(|m| m.kind == kind).map(spawn_number).max().unwrap_or(0) + 1;
+    let suffix = format!(" #{}", number);
+    let mut name = kind.to_string();
+
+    while name.len() + suffix.len() > 32 {
+        name.pop();
+    }
+
+    monster.name = format!("{}{}", name, suffix);
+    monster.current_room = room_num as u16;
+    monster.spawned = true;
+
+    if let Some(list) = map["rooms"][room_num]["monsters"].as_array_mut() {
+        list.push(Value::String(monster.name.clone()));
+    }
+
+    let name = monster.name.clone();
+
+    monsters.push(monster);
+
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn map() -> Value {
+        json!({
+            "rooms": [
+                { "id": 0, "name": "Meadow", "monsters": [] },
+                { "id": 1, "name": "Clearing", "monsters": [] }
+            ],
+            "monsters": [
+                { "name": "Moblin", "health": 20, "current_room": 0 },
+                { "name": "Keese", "health": 5, "current_room": 1 },
+                { "name": "Keese", "health": 5, "current_room": 1 }
+            ]
+        })
+    }
+
+    #[test]
+    fn monsters_placed_more_than_once_are_numbered() {
+        let mut map = map();
+        let monsters = load_monsters(&mut map);
+
+        let names = monsters.iter().map(|m| m.name.as_str()).collect::<Vec<&str>>();
+
+        assert_eq!(names, vec!["Moblin", "Keese #1", "Keese #2"]);
+        assert_eq!(map["rooms"][1]["monsters"], json!(["Keese #1", "Keese #2"]));
+    }
+
+    #[test]
+    fn spawns_follow_the_highest_number() {
+        let mut map = map();
+        let mut monsters = load_monsters(&mut map);
+
+        assert_eq!(spawn_monster(&mut map, &mut monsters, "Moblin", 1), Some(String::from("Moblin #2")));
+
+        // With #1 gone the next Keese still can't take #2's name
+        monsters.retain(|m| m.name != "Keese #1");
+
+        assert_eq!(spawn_monster(&mut map, &mut monsters, "Keese", 1), Some(String::from("Keese #3")));
+        assert_eq!(spawn_monster(&mut map, &mut monsters, "Octorok", 1), None);
+    }
+
+    #[test]
+    fn dead_spawns_make_way_for_new_ones() {
+        let mut map = map();
+        let mut monsters = load_monsters(&mut map);
+
+        for _ in 0..10 {
+            let name = spawn_monster(&mut map, &mut monsters, "Moblin", 1).unwrap();
+
+            monsters.iter_mut().find(|m| m.name == name).unwrap().health = 0;
+        }
+
+        // The map's own monsters stay put even when dead
+        monsters[0].health = 0;
+        spawn_monster(&mut map, &mut monsters, "Moblin", 0);
+
+        assert_eq!(monsters.len(), 5);
+        assert_eq!(map["rooms"][1]["monsters"], json!(["Keese #1", "Keese #2", "Moblin #2"]));
+        assert_eq!(map["rooms"][0]["monsters"], json!(["Moblin", "Moblin #3"]));
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use serde_json::Value;
+
+/// Something the player can say back to an NPC
+#[derive(Debug, Clone)]
+pub struct DialogueOption {
+    pub text: String,
+    pub next: Option<String>, // Node to go to, the conversation ends without one
+}
+
+/// One thing an NPC says, with what it hands over the first time a player reaches it
+#[derive(Debug, Clone)]
+pub struct DialogueNode {
+    pub text: String,
+    pub options: Vec<DialogueOption>,
+    pub give_item: Option<String>,
+    pub give_gold: u16,
+    pub unlock: Option<String>, // Exit from the NPC's room that opens up for the player
+    pub start_quest: Option<String>,
+}
+
+impl DialogueNode {
+    pub fn from_json(node: &Value) -> DialogueNode {
+        let options = match node["options"].as_array() {
+            Some(options) => options.iter().filter_map(|o| {
+                Some(DialogueOption {
+                    text: o["text"].as_str()?.to_string(),
+                    next: o["next"].as_str().map(|n| n.to_string()),
+                })
+            }).collect(),
+            None => Vec::new(),
+        };
+
+        DialogueNode {
+            text: node["text"].as_str().unwrap_or("...").to_string(),
+            options,
+            give_item: node["give_item"].as_str().map(|i| i.to_string()),
+            give_gold: node["give_gold"].as_u64().unwrap_or(0) as u16,
+            unlock: node["unlock"].as_str().map(|e| e.to_string()),
+            start_quest: node["start_quest"].as_str().map(|q| q.to_string()),
+        }
+    }
+
+    /// What the NPC says followed by the numbered replies
+    pub fn speech(&self) -> String {
+        let mut text = self.text.clone();
+
+        for (number, option) in self.options.iter().enumerate() {
+            text.push_str(&format!("\n{}) {}", number + 1, option.text));
+        }
+
+        text
+    }
+}
+
+/// A non-hostile character from the map that players talk to by sending it a MESSAGE
+#[derive(Debug, Clone)]
+pub struct Npc {
+    pub name: String,
+    pub description: String,
+    pub current_room: u16,
+    pub start: String, // Node every new conversation begins at
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+impl Npc {
+    pub fn from_json(npc: &Value) -> Option<Npc> {
+        let name = npc["name"].as_str()?.to_string();
+
+        let nodes = match npc["dialogue"].as_object() {
+            Some(nodes) => nodes.iter().map(|(id, node)| (id.clone(), DialogueNode::from_json(node))).collect::<HashMap<String, DialogueNode>>(),
+            None => HashMap::new(),
+        };
+
+        let start = npc["start"].as_str().unwrap_or("start").to_string();
+
+        if !nodes.contains_key(&start) {
+            eprintln!("[NPC]\t\tError: NPC '{}' has no dialogue node '{}'", name, start);
+            return None;
+        }
+
+        Some(Npc {
+            name,
+            description: npc["description"].as_str().unwrap_or("").to_string(),
+            current_room: npc["current_room"].as_u64().unwrap_or(0) as u16,
+            start,
+            nodes,
+        })
+    }
+}
+
+/// Load the NPCs from the map
+pub fn load_npcs(map: &Value) -> Vec<Npc> {
+    match map["npcs"].as_array() {
+        Some(npcs) => npcs.iter().filter_map(Npc::from_json).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Find an NPC by name, only looking in the given room
+pub fn find_room_npc<'a>(npcs: &'a [Npc], room_num: usize, name: &str) -> Option<&'a Npc> {
+    npcs.iter().find(|n| n.current_room as usize == room_num && n.name.eq_ignore_ascii_case(name))
+}
+
+/// Where each player is in their conversation with an NPC
+#[derive(Debug, Default)]
+pub struct Conversations {
+    current: HashMap<String, (String, String)>, // Character name to NPC name and node
+}
+
+impl Conversations {
+    pub fn new() -> Conversations {
+        Conversations { current: HashMap::new() }
+    }
+
+    /// Move the conversation along with what the player said, returning the node the NPC answers with.
+    /// Picking a numbered reply follows it, anything else starts the conversation over, and None means it ended.
+    pub fn talk(&mut self, npc: &Npc, character: &str, said: &str) -> Option<String> {
+        let next = match self.current.get(character) {
+            Some((name, node)) if *name == npc.name => {
+                let choice = said.trim().parse::<usize>().ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|n| npc.nodes.get(node)?.options.get(n));
+
+                match choice {
+                    Some(option) => option.next.clone(),
+                    None => Some(npc.start.clone()),
+                }
+            },
+            _ => Some(npc.start.clone()),
+        };
+
+        // Nodes missing from the map end the conversation rather than leaving the player stuck
+        match next.filter(|n| npc.nodes.contains_key(n)) {
+            Some(node) => {
+                self.current.insert(character.to_string(), (npc.name.clone(), node.clone()));
+                Some(node)
+            },
+            None => {
+                self.current.remove(character);
+                None
+            }
+        }
+    }
+
+    /// Forget a character's conversation, such as when they leave the room
+    pub fn end(&mut self, character: &str) {
+        self.current.remove(character);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn saria() -> Npc {
+        Npc::from_json(&json!({
+            "name": "Saria",
+            "current_room": 2,
+            "dialogue": {
+                "start": {
+                    "text": "Hi!",
+                    "options": [
+                        { "text": "Teach me a song", "next": "song" },
+                        { "text": "Bye" },
+                        { "text": "Where is the temple?", "next": "missing" }
+                    ]
+                },
+                "song": { "text": "Listen closely...", "give_item": "Ocarina", "give_gold": 5 }
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn npcs_need_their_start_node() {
+        assert!(Npc::from_json(&json!({ "name": "Mido", "dialogue": { "hello": { "text": "Hmph." } } })).is_none());
+        assert_eq!(saria().nodes["song"].give_gold, 5);
+    }
+
+    #[test]
+    fn numbered_replies_follow_their_option() {
+        let saria = saria();
+        let mut conversations = Conversations::new();
+
+        assert_eq!(conversations.talk(&saria, "Link", "hello"), Some(String::from("start")));
+        assert_eq!(conversations.talk(&saria, "Link", " 1 "), Some(String::from("song")));
+    }
+
+    #[test]
+    fn anything_else_starts_over() {
+        let saria = saria();
+        let mut conversations = Conversations::new();
+
+        conversations.talk(&saria, "Link", "hello");
+
+        assert_eq!(conversations.talk(&saria, "Link", "9"), Some(String::from("start")));
+        assert_eq!(conversations.talk(&saria, "Link", "0"), Some(String::from("start")));
+        assert_eq!(conversations.talk(&saria, "Link", "what?"), Some(String::from("start")));
+    }
+
+    #[test]
+    fn conversations_end_without_a_next_node() {
+        let saria = saria();
+        let mut conversations = Conversations::new();
+
+        conversations.talk(&saria, "Link", "hello");
+        assert_eq!(conversations.talk(&saria, "Link", "2"), None);
+
+        // Ending forgets where the player was, so talking again starts over
+        assert_eq!(conversations.talk(&saria, "Link", "1"), Some(String::from("start")));
+        assert_eq!(conversations.talk(&saria, "Link", "3"), None);
+    }
+
+    #[test]
+    fn ended_conversations_start_over() {
+        let saria = saria();
+        let mut conversations = Conversations::new();
+
+        conversations.talk(&saria, "Link", "hello");
+        conversations.end("Link");
+
+        assert_eq!(conversations.talk(&saria, "Link", "1"), Some(String::from("start")));
+    }
+
+    #[test]
+    fn speech_numbers_the_replies() {
+        assert_eq!(saria().nodes["start"].speech(), "Hi!\n1) Teach me a song\n2) Bye\n3) Where is the temple?");
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt::{self,Display, Formatter};
+
+/// How gold looted from monsters is handed out within a party
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LootRule {
+    Looter, // Whoever loots keeps it all
+    Split,  // Shared evenly between the members in the room, the looter keeps what doesn't divide
+}
+
+impl LootRule {
+    pub fn from_name(name: &str) -> Option<LootRule> {
+        match name.to_lowercase().as_str() {
+            "looter" => Some(LootRule::Looter),
+            "split" => Some(LootRule::Split),
+            _ => None,
+        }
+    }
+}
+
+impl Display for LootRule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LootRule::Looter => write!(f, "looter"),
+            LootRule::Split => write!(f, "split"),
+        }
+    }
+}
+
+/// A group of players who fight together and share a chat channel
+#[derive(Debug, Clone)]
+pub struct Party {
+    pub leader: String,
+    pub members: Vec<String>, // Includes the leader
+    pub invited: Vec<String>,
+    pub loot: LootRule,
+}
+
+impl Party {
+    pub fn contains(&self, name: &str) -> bool {
+        self.members.iter().any(|m| m == name)
+    }
+}
+
+impl Display for Party {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Party led by {}\n\tMembers: {}\n\tLoot: {}", self.leader, self.members.join(", "), self.loot)?;
+
+        if !self.invited.is_empty() {
+            write!(f, "\n\tInvited: {}", self.invited.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Every party on the server, each character can be in at most one
+#[derive(Debug, Default)]
+pub struct Parties {
+    parties: Vec<Party>,
+}
+
+impl Parties {
+    pub fn new() -> Parties {
+        Parties { parties: Vec::new() }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Party> {
+        self.parties.iter().find(|p| p.contains(name))
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Party> {
+        self.parties.iter_mut().find(|p| p.contains(name))
+    }
+
+    /// The character's party members, or just the character when they aren't in one
+    pub fn members(&self, name: &str) -> Vec<String> {
+        match self.find(name) {
+            Some(party) => party.members.clone(),
+            None => vec![name.to_string()],
+        }
+    }
+
+    /// Invite a character, starting a party led by the inviter if they aren't in one
+    pub fn invite(&mut self, leader: &str, name: &str) -> Result<(), String> {
+        if self.find(name).is_some() {
+            return Err(format!("{} is already in a party!", name));
+        }
+
+        if self.find(leader).is_none() {
+            self.parties.push(Party {
+                leader: leader.to_string(),
+                members: vec![leader.to_string()],
+                invited: Vec::new(),
+                loot: LootRule::Split,
+            });
+        }
+
+        let party = self.find_mut(leader).unwrap();
+
+        if party.leader != leader {
+            return Err(String::from("Only the party leader can invite players!"));
+        }
+
+        if !party.invited.iter().any(|i| i == name) {
+            party.invited.push(name.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Join the party that invited the character, returning it
+    pub fn accept(&mut self, name: &str, leader: &str) -> Result<&Party, String> {
+        if self.find(name).is_some() {
+            return Err(String::from("You are already in a party, /party leave first!"));
+        }
+
+        let party = match self.parties.iter_mut().find(|p| p.leader == leader && p.invited.iter().any(|i| i == name)) {
+            Some(party) => party,
+            None => return Err(format!("{} has not invited you to a party!", leader)),
+        };
+
+        party.invited.retain(|i| i != name);
+        party.members.push(name.to_string());
+
+        // Invitations from other parties no longer apply
+        for party in self.parties.iter_mut() {
+            party.invited.retain(|i| i != name);
+        }
+
+        Ok(self.find(name).unwrap())
+    }
+
+    /// Take a character out of their party, handing over leadership and breaking up parties left with one member.
+    /// Returns the members left behind.
+    pub fn leave(&mut self, name: &str) -> Option<Vec<String>> {
+        let index = self.parties.iter().position(|p| p.contains(name))?;
+        let party = &mut self.parties[index];
+
+        party.members.retain(|m| m != name);
+
+        if party.leader == name {
+            party.leader = party.members.first().cloned().unwrap_or_default();
+        }
+
+        let remaining = party.members.clone();
+
+        if remaining.len() < 2 {
+            self.parties.remove(index);
+        }
+
+        Some(remaining)
+    }
+
+    /// Remove a member from the leader's party
+    pub fn kick(&mut self, leader: &str, name: &str) -> Result<Vec<String>, String> {
+        match self.find(leader) {
+            Some(party) if party.leader != leader => return Err(String::from("Only the party leader can kick players!")),
+            Some(party) if !party.contains(name) || name == leader => return Err(format!("{} is not in your party!", name)),
+            Some(_) => (),
+            None => return Err(String::from("You are not in a party!")),
+        }
+
+        Ok(self.leave(name).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Alice leading a party with Bob in it
+    fn party() -> Parties {
+        let mut parties = Parties::new();
+
+        parties.invite("Alice", "Bob").unwrap();
+        parties.accept("Bob", "Alice").unwrap();
+
+        parties
+    }
+
+    #[test]
+    fn inviting_starts_a_party() {
+        let mut parties = Parties::new();
+
+        assert_eq!(parties.members("Alice"), vec!["Alice"]);
+
+        parties.invite("Alice", "Bob").unwrap();
+
+        assert_eq!(parties.find("Alice").unwrap().invited, vec!["Bob"]);
+        assert!(parties.find("Bob").is_none());
+        assert_eq!(parties.members("Alice"), vec!["Alice"]);
+    }
+
+    #[test]
+    fn only_the_invited_can_join() {
+        let mut parties = party();
+
+        assert!(parties.accept("Carol", "Alice").is_err());
+        assert!(parties.accept("Bob", "Alice").is_err());
+        assert_eq!(parties.members("Bob"), vec!["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn only_the_leader_invites_and_kicks() {
+        let mut parties = party();
+
+        assert!(parties.invite("Bob", "Carol").is_err());
+        assert!(parties.kick("Bob", "Alice").is_err());
+        assert!(parties.invite("Carol", "Bob").is_err());
+        assert!(parties.kick("Alice", "Carol").is_err());
+        assert!(parties.kick("Alice", "Alice").is_err());
+    }
+
+    #[test]
+    fn joining_drops_other_invitations() {
+        let mut parties = party();
+
+        parties.invite("Carol", "Dave").unwrap();
+        parties.invite("Alice", "Dave").unwrap();
+        parties.accept("Dave", "Alice").unwrap();
+
+        assert!(parties.find("Carol").unwrap().invited.is_empty());
+        assert!(parties.accept("Dave", "Carol").is_err());
+    }
+
+    #[test]
+    fn leaders_hand_over_when_they_leave() {
+        let mut parties = party();
+
+        parties.invite("Alice", "Carol").unwrap();
+        parties.accept("Carol", "Alice").unwrap();
+
+        assert_eq!(parties.leave("Alice"), Some(vec![String::from("Bob"), String::from("Carol")]));
+        assert_eq!(parties.find("Carol").unwrap().leader, "Bob");
+    }
+
+    #[test]
+    fn parties_of_one_break_up() {
+        let mut parties = party();
+
+        assert_eq!(parties.kick("Alice", "Bob"), Ok(vec![String::from("Alice")]));
+        assert!(parties.find("Alice").is_none());
+        assert_eq!(parties.leave("Alice"), None);
+    }
+
+    #[test]
+    fn loot_rules_by_name() {
+        assert_eq!(LootRule::from_name("Split"), Some(LootRule::Split));
+        assert_eq!(LootRule::from_name("everyone"), None);
+        assert_eq!(party().find("Bob").unwrap().loot, LootRule::Split);
+    }
+}
@@ -0,0 +1,360 @@
+use std::fmt::{self,Display, Formatter};
+use serde_json::Value;
+
+use crate::character::Character;
+use crate::item::{find_item, Item};
+
+/// Something a character has to do to finish a quest
+#[derive(Debug, Clone)]
+pub enum Objective {
+    Kill { monster: String, count: u32 },   // Kill a number of monsters of one kind
+    Reach { room: u16, name: String },      // Walk into a room
+    Deliver { item: String, npc: String },  // Talk to an NPC while carrying an item, which is handed over
+    Talk { npc: String },                   // Talk to an NPC
+}
+
+impl Objective {
+    pub fn from_json(objective: &Value) -> Option<Objective> {
+        match objective["type"].as_str()? {
+            "kill" => Some(Objective::Kill {
+                monster: objective["monster"].as_str()?.to_string(),
+                count: objective["count"].as_u64().unwrap_or(1) as u32,
+            }),
+            "reach" => {
+                let room = objective["room"].as_u64()? as u16;
+                let name = objective["name"].as_str().map(|n| n.to_string()).unwrap_or(format!("room {}", room));
+
+                Some(Objective::Reach { room, name })
+            },
+            "deliver" => Some(Objective::Deliver {
+                item: objective["item"].as_str()?.to_string(),
+                npc: objective["npc"].as_str()?.to_string(),
+            }),
+            "talk" => Some(Objective::Talk { npc: objective["npc"].as_str()?.to_string() }),
+            other => {
+                eprintln!("[QUEST]\t\tError: Unknown objective type '{}'", other);
+                None
+            }
+        }
+    }
+
+    /// How many times the objective has to happen
+    pub fn goal(&self) -> u32 {
+        match self {
+            Objective::Kill { count, .. } => *count,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for Objective {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Objective::Kill { monster, count } => write!(f, "Defeat {} {}", count, monster),
+            Objective::Reach { name, .. } => write!(f, "Find {}", name),
+            Objective::Deliver { item, npc } => write!(f, "Bring a {} to {}", item, npc),
+            Objective::Talk { npc } => write!(f, "Speak with {}", npc),
+        }
+    }
+}
+
+/// A quest from the map's "quests" list
+#[derive(Debug, Clone)]
+pub struct Quest {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub objectives: Vec<Objective>,
+    pub automatic: bool, // Given to every character when they join, otherwise started by an NPC
+    pub reward_gold: u16,
+    pub reward_experience: u32,
+    pub reward_item: Option<String>,
+}
+
+impl Quest {
+    pub fn from_json(quest: &Value) -> Option<Quest> {
+        let id = quest["id"].as_str()?.to_string();
+
+        let objectives = match quest["objectives"].as_array() {
+            Some(objectives) => objectives.iter().filter_map(Objective::from_json).collect::<Vec<Objective>>(),
+            None => Vec::new(),
+        };
+
+        if objectives.is_empty() {
+            eprintln!("[QUEST]\t\tError: Quest '{}' has no objectives", id);
+            return None;
+        }
+
+        Some(Quest {
+            name: quest["name"].as_str().unwrap_or(&id).to_string(),
+            id,
+            description: quest["description"].as_str().unwrap_or("").to_string(),
+            objectives,
+            automatic: quest["automatic"].as_bool().unwrap_or(false),
+            reward_gold: quest["reward"]["gold"].as_u64().unwrap_or(0) as u16,
+            reward_experience: quest["reward"]["experience"].as_u64().unwrap_or(0) as u32,
+            reward_item: quest["reward"]["item"].as_str().map(|i| i.to_string()),
+        })
+    }
+}
+
+/// A character's progress through one quest
+#[derive(Debug, Clone)]
+pub struct QuestProgress {
+    pub id: String,
+    pub progress: Vec<u32>, // Count for each objective
+    pub completed: bool,
+}
+
+/// Something a character did that may count towards a quest
+#[derive(Debug, Clone)]
+pub enum QuestEvent<'a> {
+    Killed(&'a str),    // Monster kind
+    Reached(u16),       // Room number
+    Talked(&'a str),    // NPC name
+}
+
+/// Load the quests from the map
+pub fn load_quests(map: &Value) -> Vec<Quest> {
+    match map["quests"].as_array() {
+        Some(quests) => quests.iter().filter_map(Quest::from_json).collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn find_quest<'a>(quests: &'a [Quest], id: &str) -> Option<&'a Quest> {
+    quests.iter().find(|q| q.id == id)
+}
+
+/// Give a character a quest they don't already have, returning whether it was started
+pub fn start_quest(quest: &Quest, character: &mut Character) -> bool {
+    if character.quests.iter().any(|q| q.id == quest.id) {
+        return false;
+    }
+
+    character.quests.push(QuestProgress {
+        id: quest.id.clone(),
+        progress: vec![0; quest.objectives.len()],
+        completed: false,
+    });
+
+    true
+}
+
+/// What an event did to a character's quests
+#[derive(Debug, Default)]
+pub struct QuestReport {
+    pub messages: Vec<String>,  // What to tell the character
+    pub received: Vec<Item>,    // Reward items added to their inventory
+    pub delivered: Vec<Item>,   // Items they handed over to finish deliveries
+}
+
+/// Count an event towards the character's quests and hand out the rewards for any finished
+pub fn advance(quests: &[Quest], items: &[Item], character: &mut Character, event: &QuestEvent) -> QuestReport {
+    let mut report = QuestReport::default();
+
+    for index in 0..character.quests.len() {
+        if character.quests[index].completed {
+            continue;
+        }
+
+        let quest = match find_quest(quests, &character.quests[index].id) {
+            Some(quest) => quest,
+            None => continue,
+        };
+
+        for (number, objective) in quest.objectives.iter().enumerate() {
+            if character.quests[index].progress[number] >= objective.goal() {
+                continue;
+            }
+
+            let counts = match (objective, event) {
+                (Objective::Kill { monster, .. }, QuestEvent::Killed(kind)) => monster == kind,
+                (Objective::Reach { room, .. }, QuestEvent::Reached(reached)) => room == reached,
+                (Objective::Talk { npc }, QuestEvent::Talked(name)) => npc == name,
+                (Objective::Deliver { item, npc }, QuestEvent::Talked(name)) if npc == name => match character.take_item(item) {
+                    Some(item) => {
+                        report.delivered.push(item);
+                        true
+                    },
+                    None => false,
+                },
+                _ => false,
+            };
+
+            if !counts {
+                continue;
+            }
+
+            character.quests[index].progress[number] += 1;
+
+            report.messages.push(format!("{}: {} ({}/{})", quest.name, objective, character.quests[index].progress[number], objective.goal()));
+        }
+
+        let done = quest.objectives.iter()
+            .zip(character.quests[index].progress.iter())
+            .all(|(objective, progress)| *progress >= objective.goal());
+
+        if done {
+            character.quests[index].completed = true;
+            reward(quest, items, character, &mut report);
+        }
+    }
+
+    report
+}
+
+fn reward(quest: &Quest, items: &[Item], character: &mut Character, report: &mut QuestReport) {
+    let messages = &mut report.messages;
+
+    messages.push(format!("Quest complete: {}!", quest.name));
+
+    if quest.reward_gold > 0 {
+        character.add_gold(quest.reward_gold);
+        messages.push(format!("You received {} gold.", quest.reward_gold));
+    }
+
+    if let Some(item) = quest.reward_item.as_ref().and_then(|name| find_item(items, name)) {
+        messages.push(format!("You received a {}.", item.name));
+        character.inventory.push(item.clone());
+        report.received.push(item);
+    }
+
+    if quest.reward_experience > 0 {
+        let levels = character.gain_experience(quest.reward_experience);
+
+        messages.push(format!("You gained {} experience!", quest.reward_experience));
+
+        if levels > 0 {
+            messages.push(format!(
+                "You reached level {}! You have {} stat point(s) to spend; send your character again with higher stats to use them.",
+                character.level, character.stat_points
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::character::tests::character;
+    use crate::item::load_items;
+
+    fn quests() -> Vec<Quest> {
+        load_quests(&json!({
+            "quests": [
+                {
+                    "id": "owl",
+                    "name": "The Wise Owl",
+                    "objectives": [
+                        { "type": "kill", "monster": "Skulltula", "count": 2 },
+                        { "type": "reach", "room": 4 },
+                        { "type": "deliver", "item": "Weird Egg", "npc": "Kaepora Gaebora" }
+                    ],
+                    "reward": { "gold": 15, "experience": 100, "item": "Ocarina" }
+                },
+                { "id": "empty", "objectives": [] }
+            ]
+        }))
+    }
+
+    fn items() -> Vec<Item> {
+        load_items(&json!({
+            "items": [
+                { "name": "Ocarina", "kind": "key" },
+                { "name": "Weird Egg", "kind": "key" }
+            ]
+        }))
+    }
+
+    #[test]
+    fn quests_need_objectives() {
+        let quests = quests();
+
+        assert_eq!(quests.len(), 1);
+        assert_eq!(quests[0].objectives[1].to_string(), "Find room 4");
+    }
+
+    #[test]
+    fn starting_a_quest_twice_does_nothing() {
+        let quests = quests();
+        let mut link = character("Link");
+
+        assert!(start_quest(&quests[0], &mut link));
+        assert!(!start_quest(&quests[0], &mut link));
+        assert_eq!(link.quests.len(), 1);
+    }
+
+    #[test]
+    fn only_quests_in_progress_advance() {
+        let (quests, items) = (quests(), items());
+        let mut link = character("Link");
+
+        assert!(advance(&quests, &items, &mut link, &QuestEvent::Killed("Skulltula")).messages.is_empty());
+
+        start_quest(&quests[0], &mut link);
+
+        assert_eq!(advance(&quests, &items, &mut link, &QuestEvent::Killed("Skulltula")).messages, vec!["The Wise Owl: Defeat 2 Skulltula (1/2)"]);
+        assert!(advance(&quests, &items, &mut link, &QuestEvent::Killed("Keese")).messages.is_empty());
+        assert!(advance(&quests, &items, &mut link, &QuestEvent::Reached(3)).messages.is_empty());
+        assert_eq!(link.quests[0].progress, vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn objectives_stop_counting_at_their_goal() {
+        let (quests, items) = (quests(), items());
+        let mut link = character("Link");
+
+        start_quest(&quests[0], &mut link);
+
+        for _ in 0..3 {
+            advance(&quests, &items, &mut link, &QuestEvent::Killed("Skulltula"));
+        }
+
+        assert_eq!(link.quests[0].progress[0], 2);
+    }
+
+    #[test]
+    fn deliveries_need_the_item_and_take_it() {
+        let (quests, items) = (quests(), items());
+        let mut link = character("Link");
+
+        start_quest(&quests[0], &mut link);
+
+        assert!(advance(&quests, &items, &mut link, &QuestEvent::Talked("Kaepora Gaebora")).messages.is_empty());
+
+        link.inventory.push(find_item(&items, "Weird Egg").unwrap());
+        let report = advance(&quests, &items, &mut link, &QuestEvent::Talked("Kaepora Gaebora"));
+
+        assert!(!link.has_item("Weird Egg"));
+        assert_eq!(report.delivered.iter().map(|i| i.name.as_str()).collect::<Vec<&str>>(), vec!["Weird Egg"]);
+        assert!(report.received.is_empty());
+        assert_eq!(link.quests[0].progress[2], 1);
+    }
+
+    #[test]
+    fn finishing_a_quest_hands_out_the_reward_once() {
+        let (quests, items) = (quests(), items());
+        let mut link = character("Link");
+
+        start_quest(&quests[0], &mut link);
+        link.inventory.push(find_item(&items, "Weird Egg").unwrap());
+
+        advance(&quests, &items, &mut link, &QuestEvent::Killed("Skulltula"));
+        advance(&quests, &items, &mut link, &QuestEvent::Killed("Skulltula"));
+        advance(&quests, &items, &mut link, &QuestEvent::Reached(4));
+
+        let report = advance(&quests, &items, &mut link, &QuestEvent::Talked("Kaepora Gaebora"));
+
+        assert_eq!(report.received.iter().map(|i| i.name.as_str()).collect::<Vec<&str>>(), vec!["Ocarina"]);
+        assert!(report.messages.contains(&String::from("Quest complete: The Wise Owl!")));
+        assert!(link.quests[0].completed);
+        assert!(link.has_item("Ocarina"));
+        assert_eq!((link.gold, link.level), (15, 2));
+
+        assert!(advance(&quests, &items, &mut link, &QuestEvent::Reached(4)).messages.is_empty());
+        assert_eq!(link.gold, 15);
+    }
+}
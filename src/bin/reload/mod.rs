@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use serde_json::Value;
+
+use crate::character::Character;
+use crate::effect::{room_effects, RoomEffect};
+use crate::exit::{room_exits, room_id};
+use crate::monster::Monster;
+
+/// Parts of the map the server only reads when it starts, so changes to them wait for a restart
+const STARTUP_ONLY: [&str; 8] = ["combat", "death", "items", "shops", "bosses", "events", "npcs", "quests"];
+
+/// What a reload did to the live world
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    pub changes: Vec<String>,
+    pub relocated: Vec<usize>, // Characters whose room disappeared, by index
+    pub forgotten: Vec<usize>, // Characters who had been to or found exits in rooms that disappeared, by index
+    pub removed: Vec<u32>,     // Monsters whose room disappeared, by id
+}
+
+/// Everything wrong with a map that would break the live world if it were loaded
+pub fn validate(map: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let rooms = match map["rooms"].as_array() {
+        Some(rooms) if !rooms.is_empty() => rooms,
+        _ => return vec![String::from("The map has no rooms")],
+    };
+
+    let mut names: Vec<&str> = Vec::new();
+
+    for (index, room) in rooms.iter().enumerate() {
+        let name = room["name"].as_str().unwrap_or("");
+
+        if room["id"].as_u64() != Some(index as u64) {
+            problems.push(format!("Room {} '{}' has id {}", index, name, room["id"]));
+        }
+
+        if name.is_empty() || name.len() > 32 {
+            problems.push(format!("Room {} needs a name of 1 to 32 characters", index));
+        }
+
+        if names.contains(&name) {
+            problems.push(format!("Room name '{}' is used more than once", name));
+        }
+
+        names.push(name);
+
+        if room["description"].as_str().is_none() {
+            problems.push(format!("Room '{}' has no description", name));
+        }
+
+        for exit in room_exits(map, index) {
+            if room_id(map, &exit.room).is_none() {
+                problems.push(format!("Room '{}' has an exit to unknown room '{}'", name, exit.room));
+            }
+        }
+
+        for effect in room_effects(map, index) {
+            if let RoomEffect::Teleport { room: target, .. } = effect {
+                if target as usize >= rooms.len() {
+                    problems.push(format!("Room '{}' teleports to room {} which doesn't exist", name, target));
+                }
+            }
+        }
+    }
+
+    for monster in map["monsters"].as_array().into_iter().flatten() {
+        let name = monster["name"].as_str().unwrap_or("");
+
+        if name.is_empty() || name.len() > 32 {
+            problems.push(format!("Monster '{}' needs a name of 1 to 32 characters", name));
+        }
+
+        if monster["current_room"].as_u64().is_some_and(|room| room as usize >= rooms.len()) {
+            problems.push(format!("Monster '{}' is placed in room {} which doesn't exist", name, monster["current_room"]));
+        }
+    }
+
+    problems
+}
+
+/// Every way a freshly loaded map would leave the parts only read at startup in the wrong rooms.
+///
+/// Shops, NPCs, quests and events keep the room numbers the server started with, so a reload may add rooms
+/// after the last one and remove rooms none of them use, but may not give an existing room a new number.
+pub fn renumbered(map: &Value, fresh: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    let used = startup_rooms(map);
+
+    for (old, room) in map["rooms"].as_array().into_iter().flatten().enumerate() {
+        let name = room["name"].as_str().unwrap_or("");
+
+        match room_id(fresh, name) {
+            Some(new) if new as usize != old => {
+                problems.push(format!("Room '{}' would move from room {} to {}, which needs a restart", name, old, new));
+            },
+            None if used.contains(&(old as u64)) => {
+                problems.push(format!("Room '{}' is used by shops, NPCs, quests or events, removing it needs a restart", name));
+            },
+            _ => {},
+        }
+    }
+
+    problems
+}
+
+/// Every room number the shops, NPCs, quests and events in the map use
+fn startup_rooms(map: &Value) -> Vec<u64> {
+    let list = |key: &str| map[key].as_array().cloned().unwrap_or_default();
+    let mut rooms = Vec::new();
+
+    rooms.extend(list("shops").iter().filter_map(|shop| shop["room"].as_u64()));
+    rooms.extend(list("npcs").iter().filter_map(|npc| npc["current_room"].as_u64()));
+
+    for event in list("events") {
+        rooms.extend(event["room"].as_u64());
+        rooms.extend(event["rooms"].as_array().into_iter().flatten().filter_map(|r| r["room"].as_u64()));
+    }
+
+    for quest in list("quests") {
+        rooms.extend(quest["objectives"].as_array().into_iter().flatten().filter_map(|o| o["room"].as_u64()));
+    }
+
+    rooms
+}
+
+/// The first template for each kind of monster in the map, without its placement
+fn templates(map: &Value) -> HashMap<String, Value> {
+    let mut templates = HashMap::new();
+
+    for monster in map["monsters"].as_array().into_iter().flatten() {
+        if let Some(name) = monster["name"].as_str() {
+            // Where a monster is placed isn't part of what it is
+            let mut template = monster.clone();
+
+            if let Some(fields) = template.as_object_mut() {
+                fields.remove("id");
+                fields.remove("current_room");
+            }
+
+            templates.entry(name.to_string()).or_insert(template);
+        }
+    }
+
+    templates
+}
+
+/// Swap the live map's rooms and monster templates for the freshly loaded ones.
+///
+/// Rooms are matched by name so characters, monsters and items on the floor stay where they are.
+/// Characters in a room that is gone are sent to the first room, monsters there are removed.
+/// Check the fresh map with `renumbered` first, the parts only read at startup aren't moved.
+pub fn apply(map: &mut Value, mut fresh: Value, characters: &mut [Character], monsters: &mut Vec<Monster>) -> ReloadReport {
+    let mut report = ReloadReport::default();
+
+    let old_rooms = map["rooms"].as_array().cloned().unwrap_or_default();
+
+    // Where each old room is in the new map
+    let moved = old_rooms.iter().enumerate()
+        .map(|(old, room)| (old as u16, room["name"].as_str().and_then(|name| room_id(&fresh, name))))
+        .collect::<HashMap<u16, Option<u16>>>();
+
+    for (old, room) in old_rooms.iter().enumerate() {
+        let name = room["name"].as_str().unwrap_or("");
+
+        let new = match moved[&(old as u16)] {
+            Some(new) => new as usize,
+            None => {
+                report.changes.push(format!("Removed room '{}'", name));
+                continue;
+            }
+        };
+
+        let new_room = &mut fresh["rooms"][new];
+
+        if new_room["description"] != room["description"] {
+            report.changes.push(format!("Changed the description of '{}'", name));
+        }
+
+        if new_room["exits"] != room["exits"] {
+            report.changes.push(format!("Changed the exits of '{}'", name));
+        }
+
+        if new_room["effects"] != room["effects"] {
+            report.changes.push(format!("Changed the effects of '{}'", name));
+        }
+
+        // Whoever and whatever is in the room right now stays there
+        new_room["characters"] = room["characters"].clone();
+        new_room["monsters"] = room["monsters"].clone();
+        new_room["items"] = room["items"].clone();
+    }
+
+    for room in fresh["rooms"].as_array().into_iter().flatten() {
+        let name = room["name"].as_str().unwrap_or("");
+
+        if room_id(map, name).is_none() {
+            report.changes.push(format!("Added room '{}'", name));
+        }
+    }
+
+    // Characters follow their rooms, or are sent back to the start if their room is gone
+    for (index, character) in characters.iter_mut().enumerate() {
+        let room = match moved.get(&character.current_room).copied().flatten() {
+            Some(room) => room,
+            None => {
+                let name = Value::String(character.name.clone());
+
+                if let Some(list) = fresh["rooms"][0]["characters"].as_array_mut() {
+                    if !list.contains(&name) {
+                        list.push(name);
+                    }
+                }
+
+                report.relocated.push(index);
+                0
+            }
+        };
+
+        let known = character.visited.len() + character.discovered.len();
+
+        character.visited = character.visited.iter().filter_map(|r| moved.get(r).copied().flatten()).collect();
+        character.discovered = character.discovered.iter()
+            .filter_map(|(r, exit)| Some((moved.get(r).copied().flatten()?, exit.clone())))
+            .collect();
+
+        if character.visited.len() + character.discovered.len() < known {
+            report.forgotten.push(index);
+        }
+
+        character.update_room(room);
+    }
+
+    monsters.retain_mut(|monster| match moved.get(&monster.current_room).copied().flatten() {
+        Some(room) => {
+            monster.current_room = room;
+            true
+        },
+        None => {
+            report.removed.push(monster.id);
+            false
+        },
+    });
+
+    if !report.removed.is_empty() {
+        report.changes.push(format!("Removed {} monster(s) whose room is gone", report.removed.len()));
+    }
+
+    // Monsters already in the world take on their kind's new stats unless a fight has changed them
+    let old_templates = templates(map);
+    let new_templates = templates(&fresh);
+
+    for (kind, template) in new_templates.iter() {
+        let old = match old_templates.get(kind) {
+            Some(old) if old == template => continue,
+            Some(old) => old,
+            None => {
+                report.changes.push(format!("Added monster '{}'", kind));
+                continue;
+            }
+        };
+
+        let before = Monster::from_json(old, 0);
+        let after = Monster::from_json(template, 0);
+
+        for monster in monsters.iter_mut().filter(|m| m.kind == *kind) {
+            monster.description = after.description.clone();
+            monster.drops = after.drops.clone();
+            monster.experience = after.experience;
+
+            if (monster.attack, monster.defense, monster.regen) == (before.attack, before.defense, before.regen) {
+                monster.attack = after.attack;
+                monster.defense = after.defense;
+                monster.regen = after.regen;
+            }
+
+            if monster.health == before.health {
+                monster.health = after.health;
+            }
+
+            if monster.gold == before.gold {
+                monster.gold = after.gold;
+            }
+        }
+
+        report.changes.push(format!("Changed monster '{}'", kind));
+    }
+
+    for kind in old_templates.keys().filter(|k| !new_templates.contains_key(*k)) {
+        report.changes.push(format!("Removed monster '{}', the ones already in the world stay", kind));
+    }
+
+    // Only the rooms and monster templates are swapped, the rest keeps matching what the server loaded
+    for key in STARTUP_ONLY {
+        if fresh[key] != map[key] {
+            report.changes.push(format!("Changes to {} need a restart", key));
+        }
+    }
+
+    map["rooms"] = fresh["rooms"].take();
+    map["monsters"] = fresh["monsters"].take();
+    map["fog_of_war"] = fresh["fog_of_war"].take();
+    map["zones"] = fresh["zones"].take();
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::character::tests::character;
+
+    fn world() -> Value {
+        json!({
+            "rooms": [
+                { "id": 0, "name": "Glade", "description": "Sunny", "exits": ["Cave"], "characters": [], "monsters": [], "items": [] },
+                { "id": 1, "name": "Cave", "description": "Dark", "exits": ["Glade", "Pit"], "characters": ["Alice"], "monsters": [1], "items": ["Torch"] },
+                { "id": 2, "name": "Pit", "description": "Deep", "exits": ["Cave"], "characters": ["Bob"], "monsters": [2], "items": [] },
+            ],
+            "monsters": [{ "name": "Bat", "attack": 5, "defense": 5, "regen": 0, "health": 10, "gold": 1, "current_room": 1 }],
+            "shops": [{ "room": 1 }],
+        })
+    }
+
+    #[test]
+    fn validate_accepts_a_good_map() {
+        assert!(validate(&world()).is_empty());
+    }
+
+    #[test]
+    fn validate_finds_problems() {
+        let mut map = world();
+        map["rooms"][1]["id"] = json!(5);
+        map["rooms"][2]["name"] = json!("Glade");
+        map["rooms"][0]["exits"] = json!(["Nowhere"]);
+        map["rooms"][0]["effects"] = json!([{ "type": "teleport", "room": 9 }]);
+        map["monsters"][0]["current_room"] = json!(3);
+
+        let problems = validate(&map);
+
+        // Renaming the Pit also leaves the Cave's exit to it leading nowhere
+        assert_eq!(problems.len(), 6, "{:?}", problems);
+        assert!(validate(&json!({ "rooms": [] }))[0].contains("no rooms"));
+    }
+
+    #[test]
+    fn renumbered_refuses_moved_rooms() {
+        let mut fresh = world();
+        fresh["rooms"].as_array_mut().unwrap().remove(0);
+        fresh["rooms"][0]["id"] = json!(0);
+        fresh["rooms"][1]["id"] = json!(1);
+
+        assert_eq!(renumbered(&world(), &fresh).len(), 2);
+    }
+
+    #[test]
+    fn renumbered_refuses_removing_used_rooms() {
+        let mut map = world();
+        map["shops"] = json!([{ "room": 2 }]);
+
+        let mut fresh = world();
+        fresh["rooms"].as_array_mut().unwrap().pop();
+
+        assert_eq!(renumbered(&map, &fresh).len(), 1);
+        assert!(renumbered(&world(), &fresh).is_empty());
+    }
+
+    #[test]
+    fn renumbered_allows_new_rooms_at_the_end() {
+        let mut fresh = world();
+        fresh["rooms"].as_array_mut().unwrap().push(json!({ "id": 3, "name": "Lake", "description": "Wet", "exits": ["Glade"] }));
+
+        assert!(renumbered(&world(), &fresh).is_empty());
+    }
+
+    #[test]
+    fn apply_keeps_the_live_world() {
+        let mut map = world();
+        let mut characters = vec![character("Alice"), character("Bob")];
+        characters[0].update_room(1);
+        characters[1].update_room(2);
+
+        let mut monsters = vec![Monster::from_json(&map["monsters"][0], 1), Monster::from_json(&map["monsters"][0], 2)];
+        monsters[1].current_room = 2;
+        monsters[1].health = 3;
+
+        let mut fresh = world();
+        fresh["rooms"].as_array_mut().unwrap().pop();
+        fresh["rooms"][1]["description"] = json!("Very dark");
+        fresh["monsters"][0]["attack"] = json!(8);
+        fresh["monsters"][0]["health"] = json!(20);
+        fresh["shops"] = json!([{ "room": 0 }]);
+
+        let report = apply(&mut map, fresh, &mut characters, &mut monsters);
+
+        assert!(report.changes.contains(&String::from("Changed the description of 'Cave'")));
+        assert!(report.changes.contains(&String::from("Removed room 'Pit'")));
+        assert!(report.changes.contains(&String::from("Changes to shops need a restart")));
+        assert_eq!(report.relocated, vec![1]);
+        assert_eq!(report.forgotten, vec![1]);
+        assert_eq!(report.removed, vec![2]);
+
+        // Alice stays with what was in her room, Bob is sent to the start
+        assert_eq!(characters[0].current_room, 1);
+        assert_eq!(map["rooms"][1]["description"], "Very dark");
+        assert_eq!(map["rooms"][1]["characters"], json!(["Alice"]));
+        assert_eq!(map["rooms"][1]["items"], json!(["Torch"]));
+        assert_eq!(characters[1].current_room, 0);
+        assert_eq!(characters[1].visited, vec![0]);
+        assert_eq!(map["rooms"][0]["characters"], json!(["Bob"]));
+
+        // The Bat in the removed room is gone, the other takes on the new stats
+        assert_eq!(monsters.len(), 1);
+        assert_eq!((monsters[0].attack, monsters[0].health), (8, 20));
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs;
+use std::path::Path;
+use std::result;
+use serde_json::Value;
+
+/// Why a map could not be read or written, for the caller to report
+type Result<T> = result::Result<T, String>;
+
+/// Newest map format the server understands.
+///
+/// 1: The original format, with no "version" and a hand counted "desc_length" for every room and monster
+/// 2: Adds "version", lengths are worked out from the descriptions so maps leave them out
+pub const MAP_VERSION: u64 = 2;
+
+/// File types a map can be written in, tried in this order when looking for a map
+const FORMATS: [&str; 4] = ["json", "toml", "yaml", "yml"];
+
+/// Find the file for a map number in any of the supported formats
+pub fn find_map(map_path: &str, map_num: u8) -> Option<String> {
+    FORMATS.iter()
+        .map(|format| format!("{}{}.{}", map_path, map_num, format))
+        .find(|path| Path::new(path).exists())
+}
+
+fn format_of(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+
+    FORMATS.iter().find(|format| **format == extension).copied()
+}
+
+/// Read a map in whatever format its extension says, bringing it up to the newest version
+pub fn read_map(path: &str) -> Result<Value> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("Could not read map file {}: {}", path, err))?;
+
+    let mut map: Value = match format_of(path) {
+        Some("json") => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+        Some("toml") => toml::from_str(&contents).map_err(|err| err.to_string()),
+        Some(_) => serde_yaml::from_str(&contents).map_err(|err| err.to_string()),
+        None => Err(String::from("unknown file type")),
+    }.map_err(|err| format!("Could not parse map file {}: {}", path, err))?;
+
+    migrate(&mut map, path)?;
+    check_lengths(&map, path)?;
+
+    Ok(map)
+}
+
+/// Write a map in whatever format the path's extension says
+pub fn write_map(path: &str, map: &Value) -> Result<()> {
+    let contents = match format_of(path) {
+        Some("json") => serde_json::to_string_pretty(map).map_err(|err| err.to_string()),
+        Some("toml") => toml::to_string_pretty(map).map_err(|err| err.to_string()),
+        Some(_) => serde_yaml::to_string(map).map_err(|err| err.to_string()),
+        None => Err(String::from("unknown file type, use .json, .toml or .yaml")),
+    }.map_err(|err| format!("Could not write map file {}: {}", path, err))?;
+
+    fs::write(path, contents).map_err(|err| format!("Could not write map file {}: {}", path, err))
+}
+
+/// Bring a map from whatever version it was written in up to the newest one
+fn migrate(map: &mut Value, path: &str) -> Result<()> {
+    let mut version = map["version"].as_u64().unwrap_or(1);
+
+    if version > MAP_VERSION {
+        return Err(format!("Map file {} is version {}, this server only understands up to version {}", path, version, MAP_VERSION));
+    }
+
+    while version < MAP_VERSION {
+        if version == 1 {
+            drop_lengths(map, path);
+        }
+
+        version += 1;
+
+        println!("[SCHEMA]\tMigrated map file {} to version {}", path, version);
+    }
+
+    map["version"] = Value::from(MAP_VERSION);
+
+    Ok(())
+}
+
+/// Version 1 to 2: remove the hand counted lengths, pointing out the ones that were wrong
+fn drop_lengths(map: &mut Value, path: &str) {
+    for list in ["rooms", "monsters"] {
+        let entries = map.get_mut(list).and_then(|l| l.as_array_mut()).into_iter().flatten();
+
+        for entry in entries.filter_map(|e| e.as_object_mut()) {
+            let counted = match entry.remove("desc_length") {
+                Some(counted) => counted.as_u64(),
+                None => continue,
+            };
+
+            let description = entry.get("description").and_then(|d| d.as_str()).unwrap_or("");
+
+            if counted != Some(description.len() as u64) {
+                println!("[SCHEMA]\t{}: desc_length of '{}' was {:?} but its description is {} bytes",
+                    path, entry.get("name").and_then(|n| n.as_str()).unwrap_or("?"), counted, description.len());
+            }
+        }
+    }
+}
+
+/// Make sure every description fits in the 16 bit length the protocol sends before it
+fn check_lengths(map: &Value, path: &str) -> Result<()> {
+    for list in ["rooms", "monsters", "items", "npcs"] {
+        for entry in map[list].as_array().into_iter().flatten() {
+            let length = entry["description"].as_str().map(|d| d.len()).unwrap_or(0);
+
+            if length > u16::MAX as usize {
+                return Err(format!("{}: the description of '{}' is {} bytes, the most is {}", path, entry["name"], length, u16::MAX));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("lurk-schema-{}-{}", std::process::id(), name)).to_string_lossy().to_string()
+    }
+
+    fn version_one() -> Value {
+        json!({
+            "name": "Forest",
+            "rooms": [
+                { "id": 0, "name": "Glade", "description": "Sunny", "desc_length": 5, "exits": ["Cave"] },
+                { "id": 1, "name": "Cave", "description": "Dark and damp", "desc_length": 4, "exits": ["Glade"] },
+            ],
+            "monsters": [{ "name": "Bat", "description": "Squeaks", "desc_length": 7, "attack": 5, "current_room": 1 }],
+        })
+    }
+
+    #[test]
+    fn migrate_drops_lengths() {
+        let mut map = version_one();
+        migrate(&mut map, "test").unwrap();
+
+        assert_eq!(map["version"], MAP_VERSION);
+        assert!(map["rooms"].as_array().unwrap().iter().all(|r| r.get("desc_length").is_none()));
+        assert!(map["monsters"][0].get("desc_length").is_none());
+        assert_eq!(map["rooms"][1]["description"], "Dark and damp");
+    }
+
+    #[test]
+    fn migrate_refuses_newer_maps() {
+        let mut map = json!({ "version": MAP_VERSION + 1, "rooms": [] });
+
+        assert!(migrate(&mut map, "test").is_err());
+    }
+
+    #[test]
+    fn check_lengths_refuses_long_descriptions() {
+        let map = json!({ "rooms": [{ "name": "Hall", "description": "x".repeat(u16::MAX as usize + 1) }] });
+
+        assert!(check_lengths(&map, "test").is_err());
+        assert!(check_lengths(&version_one(), "test").is_ok());
+    }
+
+    #[test]
+    fn maps_round_trip_through_every_format() {
+        let source = temp_path("map1.json");
+        fs::write(&source, version_one().to_string()).unwrap();
+        let map = read_map(&source);
+        fs::remove_file(&source).unwrap();
+
+        let map = map.unwrap();
+
+        for format in FORMATS {
+            let path = temp_path(&format!("map.{}", format));
+
+            write_map(&path, &map).unwrap();
+            let read = read_map(&path);
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(read.unwrap(), map, "{} changed the map", format);
+        }
+
+        assert!(write_map(&temp_path("map.txt"), &map).is_err());
+    }
+
+    #[test]
+    fn shipped_maps_round_trip() {
+        for source in ["map2.json", "map3.json"] {
+            let map = read_map(source).unwrap();
+
+            for format in ["toml", "yaml"] {
+                let path = temp_path(&format!("{}.{}", source, format));
+
+                write_map(&path, &map).unwrap();
+                let read = read_map(&path);
+                fs::remove_file(&path).unwrap();
+
+                assert_eq!(read.unwrap(), map, "{} changed {}", format, source);
+            }
+        }
+    }
+}
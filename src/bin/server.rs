@@ -2,22 +2,43 @@ use std::sync::mpsc::sync_channel;
 use dotenv::dotenv;
 use std::net::TcpListener;
 use std::sync::{Arc, Mutex};
-use std::fs::File;
 use std::{env, result, thread};
 use serde_json::Value;
 
 // Self-made modules
+pub mod config;
 pub mod error_code;
 pub mod message;
 pub mod character;
 pub mod monster;
+pub mod item;
+pub mod combat;
+pub mod boss;
+pub mod death;
+pub mod exit;
+pub mod effect;
+pub mod event;
+pub mod mailbox;
+pub mod npc;
+pub mod quest;
+pub mod reload;
+pub mod schema;
+pub mod shop;
+pub mod stats;
+pub mod party;
+pub mod trade;
+pub mod zone;
 pub mod client_thread;
 pub mod server_thread;
 pub mod utilities;
 
 use crate::client_thread::handle_client;
 use crate::server_thread::handle_server;
-use crate::monster::Monster;
+use crate::monster::{load_monsters, Monster};
+use crate::config::Config;
+use crate::zone::load_zones;
+use crate::reload::validate;
+use crate::schema::{read_map, write_map, MAP_VERSION};
 
 type Result<T> = result::Result<T, ()>;
 
@@ -27,13 +48,34 @@ type Result<T> = result::Result<T, ()>;
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 4 {
-        eprintln!("[MAIN]\t\tUsage: lurk-server <address> <port> [5050-5054] <map_num>");
+    // Bring a map file up to the newest version, converting it if the output is a different file type
+    if args.len() == 4 && args[1] == "--migrate" {
+        let map = read_map(&args[2]).map_err(|err| {
+            eprintln!("[MAIN]\t\tError: {}", err);
+        })?;
+
+        write_map(&args[3], &map).map_err(|err| {
+            eprintln!("[MAIN]\t\tError: {}", err);
+        })?;
+
+        println!("[MAIN]\t\tWrote {} as a version {} map to {}", args[2], MAP_VERSION, args[3]);
+        return Ok(());
+    }
+
+    if args.len() != 4 && args.len() != 5 {
+        eprintln!("[MAIN]\t\tUsage: lurk-server <address> <port> [5050-5054] <map_num>[,<map_num>...] [rng_seed]");
+        eprintln!("[MAIN]\t\t       lurk-server --migrate <map file> <output map file (.json, .toml or .yaml)>");
         return Err(());
     }
 
     let address = format!("{}:{}", args[1], args[2]);
-    let map_num = args[3].parse::<u8>().unwrap_or(1);
+
+    // Every map listed is loaded as a zone of the same world, the first one is where players start
+    let map_nums = args[3].split(',').map(|num| num.trim().parse::<u8>().map_err(|err| {
+        eprintln!("[MAIN]\t\tError: Could not parse map number '{}': {}", num, err);
+        eprintln!("[MAIN]\t\tUsage: lurk-server <address> <port> [5050-5054] <map_num>[,<map_num>...] [rng_seed]");
+    })).collect::<Result<Vec<u8>>>()?;
+    let map_num = map_nums[0];
 
     let listener = TcpListener::bind(&address).map_err(|_err| {
         eprintln!("[MAIN]\t\tError: Could not bind to address {address}");
@@ -56,35 +98,44 @@ fn main() -> Result<()> {
     // Load environment variables
     let map_path = env::var("MAP_PATH").expect("MAP_PATH must be set");
 
+    // Seed for all game randomness, taken from the command line, then RNG_SEED, otherwise picked at random
+    let seed = match args.get(4).cloned().or(env::var("RNG_SEED").ok()) {
+        Some(seed) => seed.parse::<u64>().map_err(|err| {
+            eprintln!("[MAIN]\t\tError: Could not parse RNG seed '{}': {}", seed, err);
+        })?,
+        None => rand::random::<u64>(),
+    };
+
+    println!("[MAIN]\t\tRNG seed: {} (replay with RNG_SEED={})", seed, seed);
+
+    let config = Config::from_env();
+
+    println!("[MAIN]\t\tConfig: {:?}", config);
+
     //Build the game map
-    let map_file = File::open(format!("{}{}.json",map_path, map_num)).map_err(|err| {
-        eprintln!("[MAIN]\t\tError: Could not read map file: {}", err);
+    let mut map: Value = load_zones(&map_path, &map_nums).map_err(|err| {
+        eprintln!("[MAIN]\t\tError: Could not load the map: {}", err);
     })?;
 
-    let mut map: Value = serde_json::from_reader(map_file).map_err(|err| {
-        eprintln!("[MAIN]\t\tError: Could not parse map file: {}", err);
-    })?;
+    // Refuse maps that would send players somewhere that doesn't exist
+    let problems = validate(&map);
 
-    // Load monsters
-    let mut monsters: Vec<Monster> = map["monsters"].as_array().unwrap().iter().map(|monster| {
-        Monster {
-            name: monster["name"].as_str().unwrap_or("ERROR").to_string(),
-            description: monster["description"].as_str().unwrap_or("SOMETHING WENT WRONG").to_string(),
-            flags: 0xF8 as u8,
-            attack: monster["attack"].as_u64().unwrap_or(0) as u16,
-            defense: monster["defense"].as_u64().unwrap_or(0) as u16,
-            regen: monster["regen"].as_u64().unwrap_or(0) as u16,
-            health: monster["health"].as_u64().unwrap_or(0) as i16,
-            gold: monster["gold"].as_u64().unwrap_or(0) as u16,
-            current_room: monster["current_room"].as_u64().unwrap_or(0) as u16
+    if !problems.is_empty() {
+        for problem in problems.iter() {
+            eprintln!("[MAIN]\t\tError: {}", problem);
         }
-    }).collect();
+
+        return Err(());
+    }
+
+    // Load monsters
+    let mut monsters: Vec<Monster> = load_monsters(&mut map);
 
     println!("[MAIN]\t\tLoaded {} monsters", monsters.len());
     
     // Spawn server thread
     println!("[MAIN]\t\tSpawning server thread");
-    thread::spawn(move || handle_server(message_receiver, &mut map, &mut monsters)); //FIXME: Somewhere the channel is being closed before the server thread is done
+    thread::spawn(move || handle_server(message_receiver, &mut map, &mut monsters, seed, config)); //FIXME: Somewhere the channel is being closed before the server thread is done
 
     // Listen to incoming connections
     for stream in listener.incoming() {
@@ -0,0 +1,66 @@
+use std::result;
+
+use crate::error_code::ErrorCode;
+use crate::exit::{room_id, visible_exits};
+use crate::utilities::{room_name, send_narration};
+use crate::server_thread::enter;
+
+use super::{Command, CommandContext, CommandRegistry};
+
+type Result<T> = result::Result<T, ()>;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command { name: "flee", usage: "/flee", help: "Escape a boss that has sealed your room, taking your party with you", handler: flee });
+}
+
+fn flee(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let caller = context.caller().clone();
+    let old_room = caller.current_room as usize;
+
+    let boss = match context.encounters.lockdown(caller.current_room, context.monsters) {
+        Some(boss) => boss.name.clone(),
+        None => return context.error(ErrorCode::Other, "There is nothing here to flee from!"),
+    };
+
+    if caller.health <= 0 {
+        return context.error(ErrorCode::Other, "Dead players cannot flee!");
+    }
+
+    // Run for the first way out the character could take anyway, fleeing never gets past a lock, guard or toll
+    let exit = visible_exits(context.map, old_room, &caller).into_iter()
+        .find(|e| e.toll == 0 && e.check(&caller, context.monsters).is_ok() && room_id(context.map, &e.room).is_some());
+
+    let (exit, new_room) = match exit {
+        Some(exit) => {
+            let room = room_id(context.map, &exit.room).unwrap() as usize;
+            (exit, room)
+        },
+        None => return context.error(ErrorCode::BadRoom, "There is nowhere to run!"),
+    };
+
+    let party = context.parties.members(&caller.name);
+
+    // Members only follow through an exit they know about and could use themselves
+    let fleeing = (0..context.characters.len())
+        .filter(|i| {
+            let c = &context.characters[*i];
+
+            c.active && c.health > 0 && c.current_room as usize == old_room && party.contains(&c.name)
+                && visible_exits(context.map, old_room, c).iter().any(|e| e.room == exit.room)
+                && exit.check(c, context.monsters).is_ok()
+        })
+        .collect::<Vec<usize>>();
+
+    for index in fleeing {
+        let character = &context.characters[index];
+
+        println!("[COMMAND]\t{} fled from {} to room {}", character.name, boss, new_room);
+
+        // A member who can't be told still gets away with the others
+        let _ = send_narration(&character.conn, &character.name, &format!("You flee from {} to {}!", boss, room_name(context.map, new_room)));
+
+        enter(context, index, new_room as u16);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use std::result;
+
+use crate::error_code::ErrorCode;
+use crate::item::{find_item, place_item, remove_room_item, room_items, ItemKind};
+use crate::utilities::send_character;
+
+use super::{Command, CommandContext, CommandRegistry};
+
+type Result<T> = result::Result<T, ()>;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command { name: "inventory", usage: "/inventory", help: "List the items you are carrying", handler: inventory });
+    registry.register(Command { name: "look", usage: "/look", help: "List the items lying in your room", handler: look });
+    registry.register(Command { name: "take", usage: "/take <item>", help: "Pick up an item from your room", handler: take });
+    registry.register(Command { name: "drop", usage: "/drop <item>", help: "Leave an item in your room", handler: drop });
+    registry.register(Command { name: "equip", usage: "/equip <item>", help: "Equip a weapon or armor from your inventory", handler: equip });
+    registry.register(Command { name: "unequip", usage: "/unequip <item>", help: "Put an equipped weapon or armor back in your inventory", handler: unequip });
+    registry.register(Command { name: "use", usage: "/use <item>", help: "Use a consumable from your inventory", handler: use_item });
+}
+
+fn inventory(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let character = context.caller().clone();
+
+    let mut lines = Vec::new();
+
+    for item in character.weapon.iter().chain(character.armor.iter()) {
+        lines.push(format!("{} (equipped)", item));
+        context.send_item(item, true, true)?;
+    }
+
+    for item in character.inventory.iter() {
+        lines.push(item.to_string());
+        context.send_item(item, false, true)?;
+    }
+
+    if lines.is_empty() {
+        return context.reply("You are not carrying anything.");
+    }
+
+    context.reply(&format!("You are carrying:\n{}", lines.join("\n")))
+}
+
+fn look(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let items = room_items(context.map, context.caller().current_room as usize);
+
+    if items.is_empty() {
+        return context.reply("There is nothing on the ground here.");
+    }
+
+    context.reply(&format!("On the ground you see: {}", items.join(", ")))
+}
+
+fn take(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let name = args.join(" ");
+    let current_room = context.caller().current_room as usize;
+
+    let item = match find_item(context.items, &name) {
+        Some(item) if room_items(context.map, current_room).iter().any(|i| i.eq_ignore_ascii_case(&name)) => item,
+        _ => return context.error(ErrorCode::Other, &format!("There is no {} here!", name)),
+    };
+
+    remove_room_item(context.map, current_room, &name);
+
+    context.reply(&format!("You picked up the {}.", item.name))?;
+    context.send_item(&item, false, true)?;
+    context.characters[context.caller].inventory.push(item);
+
+    Ok(())
+}
+
+fn drop(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let name = args.join(" ");
+    let caller = context.caller;
+
+    let item = match context.characters[caller].take_item(&name) {
+        Some(item) => item,
+        None => return context.error(ErrorCode::Other, &format!("You are not carrying a {} (unequip it first)!", name)),
+    };
+
+    let current_room = context.caller().current_room as usize;
+
+    place_item(context.map, current_room, &item.name);
+
+    context.reply(&format!("You dropped the {}.", item.name))?;
+    context.send_item(&item, false, false)
+}
+
+fn equip(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let name = args.join(" ");
+    let caller = context.caller;
+
+    let item = match context.characters[caller].take_item(&name) {
+        Some(item) => item,
+        None => return context.error(ErrorCode::Other, &format!("You are not carrying a {}!", name)),
+    };
+
+    if !item.equippable() {
+        context.characters[caller].inventory.push(item);
+        return context.error(ErrorCode::Other, &format!("You cannot equip {}!", name));
+    }
+
+    if !context.caller().within_stat_limit(&item) {
+        context.characters[caller].inventory.push(item);
+        return context.error(ErrorCode::StatError, "Equipping that would exceed the stat limit!");
+    }
+
+    let previous = match item.kind {
+        ItemKind::Weapon => context.caller().weapon.clone(),
+        _ => context.caller().armor.clone(),
+    };
+
+    context.characters[caller].equip(item.clone());
+
+    if let Some(previous) = previous {
+        context.send_item(&previous, false, true)?;
+    }
+
+    context.reply(&format!("You equipped the {}.", item))?;
+    context.send_item(&item, true, true)?;
+
+    send_character(context.author, context.caller())
+}
+
+fn unequip(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let name = args.join(" ");
+    let caller = context.caller;
+
+    if !context.characters[caller].unequip(&name) {
+        return context.error(ErrorCode::Other, &format!("You do not have a {} equipped!", name));
+    }
+
+    let item = context.caller().inventory.last().cloned().unwrap();
+
+    context.reply(&format!("You unequipped the {}.", item.name))?;
+    context.send_item(&item, false, true)?;
+
+    send_character(context.author, context.caller())
+}
+
+fn use_item(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let name = args.join(" ");
+    let caller = context.caller;
+
+    let item = match context.characters[caller].take_item(&name) {
+        Some(item) => item,
+        None => return context.error(ErrorCode::Other, &format!("You are not carrying a {}!", name)),
+    };
+
+    if item.kind != ItemKind::Consumable {
+        context.characters[caller].inventory.push(item);
+        return context.error(ErrorCode::Other, &format!("You cannot use {} like that!", name));
+    }
+
+    if !context.caller().within_stat_limit(&item) {
+        context.characters[caller].inventory.push(item);
+        return context.error(ErrorCode::StatError, "Using that would exceed the stat limit!");
+    }
+
+    context.characters[caller].consume(&item);
+
+    context.reply(&format!("You used the {}.", item))?;
+    context.send_item(&item, false, false)?;
+
+    send_character(context.author, context.caller())
+}
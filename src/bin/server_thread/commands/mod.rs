@@ -0,0 +1,460 @@
+use std::sync::Arc;
+use std::net::TcpStream;
+use serde_json::Value;
+use std::result;
+use rand::rngs::StdRng;
+
+use crate::boss::Encounters;
+use crate::character::Character;
+use crate::config::Config;
+use crate::death::DeathRules;
+use crate::error_code::ErrorCode;
+use crate::event::Scheduler;
+use crate::exit::{fog_of_war, room_exits, room_id, visible_exits};
+use crate::item::Item;
+use crate::monster::Monster;
+use crate::npc::{Conversations, Npc};
+use crate::quest::{find_quest, Quest};
+use crate::party::Parties;
+use crate::shop::Shop;
+use crate::stats::StatsBook;
+use crate::trade::Trades;
+use crate::zone::place_name;
+use crate::utilities::{room_name, send_connections, send_error, send_item, send_narration};
+
+mod boss;
+mod items;
+mod party;
+mod reload;
+mod shop;
+mod stats;
+mod trade;
+
+type Result<T> = result::Result<T, ()>;
+
+/// Everything a command may read or change while it runs
+pub struct CommandContext<'a> {
+    pub author: &'a Arc<TcpStream>,
+    pub caller: usize, // Index of the calling character in `characters`
+    pub characters: &'a mut Vec<Character>,
+    pub map: &'a mut Value,
+    pub monsters: &'a mut Vec<Monster>,
+    pub items: &'a Vec<Item>,
+    pub item_clients: &'a Vec<Arc<TcpStream>>,
+    pub quests: &'a [Quest],
+    pub shops: &'a [Shop],
+    pub trades: &'a mut Trades,
+    pub parties: &'a mut Parties,
+    pub encounters: &'a mut Encounters,
+    pub npcs: &'a [Npc],
+    pub conversations: &'a mut Conversations,
+    pub scheduler: &'a Scheduler,
+    pub stats: &'a mut StatsBook,
+    pub death: &'a DeathRules,
+    pub rng: &'a mut StdRng,
+    pub config: &'a Config,
+}
+
+impl CommandContext<'_> {
+    /// The character who sent the command
+    pub fn caller(&self) -> &Character {
+        &self.characters[self.caller]
+    }
+
+    /// Send a narration reply to the caller
+    pub fn reply(&self, text: &str) -> Result<()> {
+        send_narration(self.author, &self.caller().name, text)
+    }
+
+    /// Send an error reply to the caller
+    pub fn error(&self, error: ErrorCode, text: &str) -> Result<()> {
+        send_error(self.author, error, text)
+    }
+
+    /// Send a narration to another active character
+    pub fn tell(&self, name: &str, text: &str) -> Result<()> {
+        match self.characters.iter().find(|c| c.name == name && c.active) {
+            Some(character) => send_narration(&character.conn, &character.name, text),
+            None => Ok(()),
+        }
+    }
+
+    /// Describe an item to the caller if their client understands the ITEM extension
+    pub fn send_item(&self, item: &Item, equipped: bool, carried: bool) -> Result<()> {
+        if !self.item_clients.iter().any(|c| Arc::ptr_eq(c, self.author)) {
+            return Ok(());
+        }
+
+        send_item(self.author, item, equipped, carried)
+    }
+
+    /// Describe an item to another active character if their client understands the ITEM extension
+    pub fn send_item_to(&self, name: &str, item: &Item, equipped: bool, carried: bool) -> Result<()> {
+        match self.characters.iter().find(|c| c.name == name && c.active) {
+            Some(character) if self.item_clients.iter().any(|c| Arc::ptr_eq(c, &character.conn)) => send_item(&character.conn, item, equipped, carried),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub type Handler = fn(&CommandRegistry, &mut CommandContext, &[&str]) -> Result<()>;
+
+/// A slash command players can send to the server through MESSAGE
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub handler: Handler,
+}
+
+/// Lookup table of the commands the server understands
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    /// An empty registry, see `CommandRegistry::builtin` for the built in commands
+    fn new() -> CommandRegistry {
+        CommandRegistry { commands: Vec::new() }
+    }
+
+    /// Add a command, replacing any existing command with the same name
+    pub fn register(&mut self, command: Command) {
+        self.commands.retain(|c| c.name != command.name);
+        self.commands.push(command);
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn commands(&self) -> &Vec<Command> {
+        &self.commands
+    }
+
+    /// Parse a command line (with or without the leading '/') and run it
+    pub fn dispatch(&self, context: &mut CommandContext, line: &str) -> Result<()> {
+        let line = line.trim().trim_start_matches('/');
+        let args = line.split_whitespace().collect::<Vec<&str>>();
+
+        let name = match args.first() {
+            Some(name) => *name,
+            None => return context.error(ErrorCode::Other, "Empty command, try /help"),
+        };
+
+        // Only the name is logged, the arguments of admin commands hold the admin token
+        println!("[COMMAND]\t{} ran command: {}", context.caller().name, name);
+
+        match self.find(name) {
+            Some(command) => (command.handler)(self, context, &args[1..]),
+            None => context.error(ErrorCode::Other, &format!("Unknown command '{}', try /help", name)),
+        }
+    }
+
+    /// A registry with every command the server understands
+    pub fn builtin() -> CommandRegistry {
+        let mut registry = CommandRegistry::new();
+
+        registry.register(Command { name: "help", usage: "/help [command]", help: "List the commands or describe one", handler: help });
+        registry.register(Command { name: "who", usage: "/who", help: "List the players in the world", handler: who });
+        registry.register(Command { name: "where", usage: "/where <player>", help: "Find which room a player is in", handler: locate });
+        registry.register(Command { name: "stats", usage: "/stats", help: "Show your character's stats", handler: stats });
+        registry.register(Command { name: "map", usage: "/map", help: "Show your room, where you can go from it and every room you have explored", handler: map });
+        registry.register(Command { name: "quests", usage: "/quests", help: "Show your quests and how far along they are", handler: quests });
+        registry.register(Command { name: "time", usage: "/time", help: "Tell whether it is day or night", handler: time });
+        registry.register(Command { name: "search", usage: "/search", help: "Look around your room for hidden exits", handler: search });
+
+        items::register(&mut registry);
+        shop::register(&mut registry);
+        trade::register(&mut registry);
+        party::register(&mut registry);
+        boss::register(&mut registry);
+        stats::register(&mut registry);
+        reload::register(&mut registry);
+
+        registry
+    }
+}
+
+fn help(registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    if let Some(name) = args.first() {
+        return match registry.find(name) {
+            Some(command) => context.reply(&format!("{} - {}", command.usage, command.help)),
+            None => context.error(ErrorCode::Other, &format!("Unknown command '{}'", name)),
+        };
+    }
+
+    let lines = registry.commands().iter()
+        .map(|c| format!("{} - {}", c.usage, c.help))
+        .collect::<Vec<String>>();
+
+    context.reply(&format!("Commands:\n{}", lines.join("\n")))
+}
+
+fn who(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let players = context.characters.iter()
+        .filter(|c| c.active)
+        .map(|c| format!("{} ({})", c.name, room_name(context.map, c.current_room as usize)))
+        .collect::<Vec<String>>();
+
+    context.reply(&format!("{} player(s) online:\n{}", players.len(), players.join("\n")))
+}
+
+fn locate(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    if args.is_empty() {
+        return context.error(ErrorCode::Other, "Usage: /where <player>");
+    }
+
+    let name = args.join(" ");
+
+    let text = match context.characters.iter().find(|c| c.name == name) {
+        Some(character) if character.active => format!("{} is in {}.", character.name, place_name(context.map, character.current_room)),
+        Some(character) => format!("{} was last seen in {}.", character.name, place_name(context.map, character.current_room)),
+        None => return context.error(ErrorCode::NoTarget, &format!("There is no character named {}!", name)),
+    };
+
+    context.reply(&text)
+}
+
+fn stats(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let text = format!("Your stats:{}", context.caller());
+
+    context.reply(&text)
+}
+
+fn map(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let current_room = context.caller().current_room as usize;
+
+    let exits = visible_exits(context.map, current_room, context.caller()).iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<String>>();
+
+    let mut lines = vec![format!("You are in {}.\nExits: {}", place_name(context.map, current_room as u16), exits.join(", "))];
+
+    lines.extend(explored(context.map, context.caller()));
+
+    context.reply(&lines.join("\n"))
+}
+
+/// Draw the rooms the character has been to and the ways between them, marking where they haven't been yet
+fn explored(map: &Value, character: &Character) -> Vec<String> {
+    let total = map["rooms"].as_array().map(|rooms| rooms.len()).unwrap_or(0);
+    let fog = fog_of_war(map);
+
+    let mut visited = character.visited.clone();
+    visited.sort();
+
+    let mut lines = vec![format!("Explored {} of {} rooms:", visited.len(), total)];
+
+    for room in visited {
+        let exits = visible_exits(map, room as usize, character).iter()
+            .map(|exit| match room_id(map, &exit.room) {
+                Some(id) if character.visited(id) => exit.room.clone(),
+                _ if fog => String::from("???"),
+                _ => format!("{} (unexplored)", exit.room),
+            })
+            .collect::<Vec<String>>();
+
+        let here = if room == character.current_room { " (you are here)" } else { "" };
+
+        lines.push(format!("  [{}] {}{} -> {}", room, room_name(map, room as usize), here, exits.join(", ")));
+    }
+
+    lines
+}
+
+fn search(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let current_room = context.caller().current_room;
+
+    let found = room_exits(context.map, current_room as usize).into_iter()
+        .filter(|e| e.hidden && !context.caller().discovered(current_room, &e.room))
+        .map(|e| e.room)
+        .collect::<Vec<String>>();
+
+    if found.is_empty() {
+        return context.reply("You search the area but find nothing new.");
+    }
+
+    let caller = &mut context.characters[context.caller];
+
+    caller.discovered.extend(found.iter().map(|room| (current_room, room.clone())));
+
+    context.reply(&format!("You found a hidden way to {}!", found.join(", ")))?;
+
+    // Let the client know about the new connections
+    send_connections(context.author, context.map, current_room as usize, context.caller())
+}
+
+fn time(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    if context.scheduler.is_night() {
+        return context.reply("It is night.");
+    }
+
+    context.reply("It is day.")
+}
+
+fn quests(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let caller = context.caller();
+
+    if caller.quests.is_empty() {
+        return context.reply("You don't have any quests.");
+    }
+
+    let mut lines = Vec::new();
+
+    for progress in caller.quests.iter() {
+        let quest = match find_quest(context.quests, &progress.id) {
+            Some(quest) => quest,
+            None => continue,
+        };
+
+        if progress.completed {
+            lines.push(format!("{} (complete)", quest.name));
+            continue;
+        }
+
+        lines.push(format!("{} - {}", quest.name, quest.description));
+
+        for (objective, count) in quest.objectives.iter().zip(progress.progress.iter()) {
+            lines.push(format!("  {} ({}/{})", objective, count, objective.goal()));
+        }
+    }
+
+    context.reply(&format!("Quests:\n{}", lines.join("\n")))
+}
+
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use crate::character::tests::character;
+
+    /// Run something with a context whose caller is the first character, returning what was sent to them
+    pub fn run_in(map: &mut Value, characters: &mut Vec<Character>, action: impl FnOnce(&mut CommandContext)) -> Vec<u8> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let author = Arc::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        let (mut peer, _) = listener.accept().unwrap();
+
+        characters[0].conn = author.clone();
+
+        let scheduler = Scheduler::new(map);
+        let mut stats = StatsBook::load(&std::env::temp_dir().join("lurk-commands-missing.json").to_string_lossy());
+        let config = Config { player_loot: false, admin_token: None, stats_path: String::new(), stats_export: String::new() };
+
+        let mut context = CommandContext {
+            author: &author,
+            caller: 0,
+            characters,
+            map,
+            monsters: &mut Vec::new(),
+            items: &Vec::new(),
+            item_clients: &Vec::new(),
+            quests: &[],
+            shops: &[],
+            trades: &mut Trades::new(),
+            parties: &mut Parties::new(),
+            encounters: &mut Encounters::new(),
+            npcs: &[],
+            conversations: &mut Conversations::new(),
+            scheduler: &scheduler,
+            stats: &mut stats,
+            death: &DeathRules::from_map(&Value::Null),
+            rng: &mut rand::SeedableRng::seed_from_u64(0),
+            config: &config,
+        };
+
+        action(&mut context);
+
+        let mut sent = Vec::new();
+        peer.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        let _ = peer.read_to_end(&mut sent);
+
+        sent
+    }
+
+    /// Run something with a context whose caller is Alice in a one room world, returning her and what was sent to her
+    fn run(action: impl FnOnce(&mut CommandContext)) -> (Character, Vec<u8>) {
+        let mut characters = vec![character("Alice")];
+        let mut map = serde_json::json!({ "rooms": [{ "id": 0, "name": "Glade", "description": "Sunny" }] });
+
+        let sent = run_in(&mut map, &mut characters, action);
+
+        (characters.remove(0), sent)
+    }
+
+    fn first(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+        context.characters[context.caller].gold = 1;
+        Ok(())
+    }
+
+    fn second(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+        context.characters[context.caller].gold = 100 + args.len() as u16;
+        Ok(())
+    }
+
+    fn registry() -> CommandRegistry {
+        let mut registry = CommandRegistry::new();
+
+        registry.register(Command { name: "ping", usage: "/ping", help: "First", handler: first });
+        registry.register(Command { name: "ping", usage: "/ping [args]", help: "Second", handler: second });
+
+        registry
+    }
+
+    /// Whether the text was sent somewhere in the bytes
+    pub fn contains(sent: &[u8], text: &str) -> bool {
+        sent.windows(text.len()).any(|w| w == text.as_bytes())
+    }
+
+    #[test]
+    fn register_replaces_commands_with_the_same_name() {
+        let registry = registry();
+
+        assert_eq!(registry.commands().len(), 1);
+        assert_eq!(registry.commands()[0].help, "Second");
+    }
+
+    #[test]
+    fn find_ignores_case() {
+        let registry = registry();
+
+        assert!(registry.find("PING").is_some());
+        assert!(registry.find("Ping").is_some());
+        assert!(registry.find("pong").is_none());
+    }
+
+    #[test]
+    fn dispatch_runs_commands_with_their_arguments() {
+        let registry = registry();
+
+        let (alice, _) = run(|context| registry.dispatch(context, "/ping a b").unwrap());
+        assert_eq!(alice.gold, 102);
+
+        let (alice, _) = run(|context| registry.dispatch(context, "  ping  ").unwrap());
+        assert_eq!(alice.gold, 100);
+    }
+
+    #[test]
+    fn dispatch_explains_bad_commands() {
+        let registry = registry();
+
+        let (_, sent) = run(|context| registry.dispatch(context, "/").unwrap());
+        assert_eq!(sent[0], 7);
+        assert!(contains(&sent, "Empty command, try /help"));
+
+        let (_, sent) = run(|context| registry.dispatch(context, "/pong").unwrap());
+        assert_eq!(sent[0], 7);
+        assert!(contains(&sent, "Unknown command 'pong', try /help"));
+    }
+
+    #[test]
+    fn builtin_commands_are_all_described() {
+        let registry = CommandRegistry::builtin();
+
+        assert!(registry.find("help").is_some());
+        assert!(registry.commands().iter().all(|c| c.usage.starts_with(&format!("/{}", c.name))));
+    }
+}
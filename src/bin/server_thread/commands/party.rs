@@ -0,0 +1,110 @@
+use std::result;
+
+use crate::error_code::ErrorCode;
+use crate::party::LootRule;
+
+use super::{Command, CommandContext, CommandRegistry};
+
+type Result<T> = result::Result<T, ()>;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command {
+        name: "party",
+        usage: "/party [invite <player> | accept <leader> | leave | kick <player> | loot <split|looter>]",
+        help: "Show your party or manage it; message \"party\" to talk to its members",
+        handler: party,
+    });
+}
+
+fn party(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let caller = context.caller().name.clone();
+    let name = args.get(1..).unwrap_or(&[]).join(" ");
+
+    match args.first().map(|a| a.to_lowercase()).as_deref() {
+        None => match context.parties.find(&caller) {
+            Some(party) => context.reply(&party.to_string()),
+            None => context.reply("You are not in a party, try /party invite <player>"),
+        },
+        Some("invite") => invite(context, &caller, &name),
+        Some("accept") => accept(context, &caller, &name),
+        Some("leave") => {
+            let remaining = match context.parties.leave(&caller) {
+                Some(remaining) => remaining,
+                None => return context.error(ErrorCode::Other, "You are not in a party!"),
+            };
+
+            for member in remaining.iter() {
+                context.tell(member, &format!("{} left the party.", caller))?;
+            }
+
+            context.reply("You left the party.")
+        },
+        Some("kick") => {
+            let remaining = match context.parties.kick(&caller, &name) {
+                Ok(remaining) => remaining,
+                Err(reason) => return context.error(ErrorCode::Other, &reason),
+            };
+
+            for member in remaining.iter().filter(|m| **m != caller) {
+                context.tell(member, &format!("{} was removed from the party.", name))?;
+            }
+
+            context.tell(&name, &format!("{} removed you from the party.", caller))?;
+            context.reply(&format!("You removed {} from the party.", name))
+        },
+        Some("loot") => {
+            let rule = match LootRule::from_name(&name) {
+                Some(rule) => rule,
+                None => return context.error(ErrorCode::Other, "Usage: /party loot <split|looter>"),
+            };
+
+            let members = match context.parties.find_mut(&caller) {
+                Some(party) if party.leader == caller => {
+                    party.loot = rule;
+                    party.members.clone()
+                },
+                Some(_) => return context.error(ErrorCode::Other, "Only the party leader can change the loot rule!"),
+                None => return context.error(ErrorCode::Other, "You are not in a party!"),
+            };
+
+            for member in members.iter() {
+                context.tell(member, &format!("Loot is now shared by the {} rule.", rule))?;
+            }
+
+            Ok(())
+        },
+        Some(other) => context.error(ErrorCode::Other, &format!("Unknown party command '{}', try /help party", other)),
+    }
+}
+
+fn invite(context: &mut CommandContext, caller: &str, name: &str) -> Result<()> {
+    match context.characters.iter().find(|c| c.name == name && c.active) {
+        Some(character) if character.name == caller => return context.error(ErrorCode::NoTarget, "You cannot invite yourself!"),
+        Some(_) => (),
+        None => return context.error(ErrorCode::NoTarget, &format!("There is no player named {} online!", name)),
+    }
+
+    if let Err(reason) = context.parties.invite(caller, name) {
+        return context.error(ErrorCode::Other, &reason);
+    }
+
+    println!("[COMMAND]\t{} invited {} to their party", caller, name);
+
+    context.tell(name, &format!("{} invited you to their party, /party accept {} to join.", caller, caller))?;
+    context.reply(&format!("You invited {} to your party.", name))
+}
+
+fn accept(context: &mut CommandContext, caller: &str, leader: &str) -> Result<()> {
+    let members = match context.parties.accept(caller, leader) {
+        Ok(party) => party.members.clone(),
+        Err(reason) => return context.error(ErrorCode::Other, &reason),
+    };
+
+    println!("[COMMAND]\t{} joined {}'s party", caller, leader);
+
+    for member in members.iter().filter(|m| *m != caller) {
+        context.tell(member, &format!("{} joined the party.", caller))?;
+    }
+
+    context.reply(&format!("You joined {}'s party with {}.", leader, members.join(", ")))
+}
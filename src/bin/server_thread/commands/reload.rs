@@ -0,0 +1,106 @@
+use std::env;
+use std::result;
+
+use crate::error_code::ErrorCode;
+use crate::reload::{apply, renumbered, validate};
+use crate::utilities::{room_name, send_connections, send_narration, send_room, send_room_npcs};
+use crate::zone::reload_zones;
+
+use super::{Command, CommandContext, CommandRegistry};
+
+type Result<T> = result::Result<T, ()>;
+
+/// Most problems or changes listed in a reply
+const REPORT_LINES: usize = 15;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command { name: "reload", usage: "/reload <admin token>", help: "Load the map files again and apply room and monster changes to the live world (admins only)", handler: reload });
+}
+
+fn reload(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let caller = context.caller().name.clone();
+
+    if !context.config.is_admin(&args.join(" ")) {
+        return context.error(ErrorCode::Other, "Only admins can reload the map!");
+    }
+
+    let map_path = match env::var("MAP_PATH") {
+        Ok(map_path) => map_path,
+        Err(_) => return context.error(ErrorCode::Other, "MAP_PATH is not set!"),
+    };
+
+    let fresh = match reload_zones(&map_path, context.map) {
+        Ok(fresh) => fresh,
+        Err(err) => {
+            eprintln!("[COMMAND]\tError: Could not reload the map: {}", err);
+
+            return context.error(ErrorCode::Other, &format!("Could not load the map files, nothing was changed: {}", err));
+        }
+    };
+
+    let mut problems = validate(&fresh);
+    problems.extend(renumbered(context.map, &fresh));
+
+    if !problems.is_empty() {
+        eprintln!("[COMMAND]\tError: Reloaded map has {} problem(s): {:?}", problems.len(), problems);
+
+        return context.reply(&summary("The map has problems, nothing was changed:", &problems));
+    }
+
+    let report = apply(context.map, fresh, context.characters, context.monsters);
+
+    println!("[COMMAND]\t{} reloaded the map: {:?}", caller, report.changes);
+
+    // Fights against bosses that went with their room are over
+    for monster in report.removed.iter() {
+        if context.encounters.end(*monster) {
+            println!("[COMMAND]\tEnded the fight against monster {}, its room is gone", monster);
+        }
+    }
+
+    // Show everyone the world as it is now
+    for index in 0..context.characters.len() {
+        let character = &context.characters[index];
+        let room_num = character.current_room as usize;
+
+        if report.relocated.contains(&index) {
+            context.stats.visit(&character.name, room_num as u16);
+        }
+
+        if !character.active {
+            continue;
+        }
+
+        if report.relocated.contains(&index) {
+            let _ = send_narration(&character.conn, &character.name, &format!("The world shifts around you and you find yourself in {}.", room_name(context.map, room_num)));
+        }
+
+        if report.forgotten.contains(&index) {
+            let _ = send_narration(&character.conn, &character.name, "Some places you knew are gone from the world, and from your memory.");
+        }
+
+        // A player whose connection is gone shouldn't stop the others from seeing the new world
+        let _ = send_room(&character.conn, context.map, context.characters, context.monsters, room_num, room_num);
+        let _ = send_room_npcs(&character.conn, context.npcs, room_num);
+        let _ = send_connections(&character.conn, context.map, room_num, character);
+    }
+
+    if report.changes.is_empty() {
+        return context.reply("Reloaded the map, nothing changed.");
+    }
+
+    context.reply(&summary(&format!("Reloaded the map with {} change(s):", report.changes.len()), &report.changes))
+}
+
+/// A heading followed by the first lines of a list
+fn summary(heading: &str, lines: &[String]) -> String {
+    let mut text = vec![heading.to_string()];
+
+    text.extend(lines.iter().take(REPORT_LINES).map(|line| format!("  {}", line)));
+
+    if lines.len() > REPORT_LINES {
+        text.push(format!("  ...and {} more", lines.len() - REPORT_LINES));
+    }
+
+    text.join("\n")
+}
@@ -0,0 +1,125 @@
+use std::result;
+
+use crate::error_code::ErrorCode;
+use crate::item::find_item;
+use crate::shop::{find_shop, Shop};
+use crate::utilities::send_character;
+
+use super::{Command, CommandContext, CommandRegistry};
+
+type Result<T> = result::Result<T, ()>;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command { name: "shop", usage: "/shop", help: "List what the shop in your room sells", handler: shop });
+    registry.register(Command { name: "buy", usage: "/buy <item>", help: "Buy an item or boost from the shop in your room", handler: buy });
+    registry.register(Command { name: "sell", usage: "/sell <item>", help: "Sell an item from your inventory to the shop in your room", handler: sell });
+}
+
+/// The open shop in the caller's room, sending an error if there isn't one
+fn room_shop<'a>(context: &CommandContext<'a>) -> result::Result<&'a Shop, ()> {
+    let shop = match find_shop(context.shops, context.caller().current_room as usize) {
+        Some(shop) => shop,
+        None => {
+            context.error(ErrorCode::Other, "There is no shop here!")?;
+            return Err(());
+        }
+    };
+
+    if !shop.is_open(context.monsters) {
+        context.error(ErrorCode::Other, &format!("{} is in no state to trade.", shop.keeper))?;
+        return Err(());
+    }
+
+    Ok(shop)
+}
+
+fn shop(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let shop = match room_shop(context) {
+        Ok(shop) => shop,
+        Err(_) => return Ok(()),
+    };
+
+    let mut lines = vec![format!("{} sells:", shop.keeper)];
+
+    for listing in shop.stock.iter() {
+        let item = match find_item(context.items, &listing.item) {
+            Some(item) => item,
+            None => continue,
+        };
+
+        let boost = if listing.apply { " (used at once)" } else { "" };
+
+        lines.push(format!("{} - {} gold{}", item, listing.price, boost));
+    }
+
+    lines.push(format!("{} buys items for {}% of their value. You have {} gold.", shop.keeper, shop.buy_rate, context.caller().gold));
+
+    context.reply(&lines.join("\n"))
+}
+
+fn buy(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let name = args.join(" ");
+    let caller = context.caller;
+
+    let shop = match room_shop(context) {
+        Ok(shop) => shop,
+        Err(_) => return Ok(()),
+    };
+
+    let (listing, item) = match shop.listing(&name).and_then(|l| Some((l, find_item(context.items, &l.item)?))) {
+        Some(found) => found,
+        None => return context.error(ErrorCode::Other, &format!("{} does not sell {}!", shop.keeper, name)),
+    };
+
+    if listing.apply && !context.caller().within_stat_limit(&item) {
+        return context.error(ErrorCode::StatError, "That boost would exceed the stat limit!");
+    }
+
+    if !context.characters[caller].spend_gold(listing.price) {
+        return context.error(ErrorCode::Other, &format!("The {} costs {} gold, you only have {}!", item.name, listing.price, context.caller().gold));
+    }
+
+    println!("[SHOP]\t\t{} bought {} from {} for {} gold", context.caller().name, item.name, shop.keeper, listing.price);
+
+    if listing.apply {
+        context.characters[caller].consume(&item);
+        context.reply(&format!("You paid {} gold for the {} and feel its effect at once.", listing.price, item))?;
+    } else {
+        context.reply(&format!("You paid {} gold for the {}.", listing.price, item.name))?;
+        context.send_item(&item, false, true)?;
+        context.characters[caller].inventory.push(item);
+    }
+
+    send_character(context.author, context.caller())
+}
+
+fn sell(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let name = args.join(" ");
+    let caller = context.caller;
+
+    let shop = match room_shop(context) {
+        Ok(shop) => shop,
+        Err(_) => return Ok(()),
+    };
+
+    let offer = match context.caller().inventory.iter().find(|i| i.name.eq_ignore_ascii_case(&name)) {
+        Some(item) => shop.offer(item),
+        None => return context.error(ErrorCode::Other, &format!("You are not carrying a {} (unequip it first)!", name)),
+    };
+
+    let offer = match offer {
+        Some(offer) => offer,
+        None => return context.error(ErrorCode::Other, &format!("{} has no use for the {}.", shop.keeper, name)),
+    };
+
+    let item = context.characters[caller].take_item(&name).unwrap();
+
+    context.characters[caller].add_gold(offer);
+
+    println!("[SHOP]\t\t{} sold {} to {} for {} gold", context.caller().name, item.name, shop.keeper, offer);
+
+    context.reply(&format!("You sold the {} for {} gold.", item.name, offer))?;
+    context.send_item(&item, false, false)?;
+
+    send_character(context.author, context.caller())
+}
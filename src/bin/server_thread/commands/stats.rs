@@ -0,0 +1,82 @@
+use std::result;
+
+use crate::error_code::ErrorCode;
+use crate::stats::Category;
+
+use super::{Command, CommandContext, CommandRegistry};
+
+type Result<T> = result::Result<T, ()>;
+
+/// How many characters a leaderboard lists
+const LEADERBOARD_SIZE: usize = 10;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command { name: "top", usage: "/top [kills|deaths|gold|rooms|time]", help: "Show the leaderboard for a statistic, or the leader of each", handler: top });
+    registry.register(Command { name: "export", usage: "/export <admin token>", help: "Write every player's statistics to a CSV file (admins only)", handler: export });
+}
+
+fn top(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let category = match args.first() {
+        Some(name) => match Category::from_name(name) {
+            Some(category) => category,
+            None => return context.error(ErrorCode::Other, "Usage: /top [kills|deaths|gold|rooms|time]"),
+        },
+        None => {
+            let mut lines = vec![String::from("Leaders:")];
+
+            for category in Category::ALL {
+                match context.stats.top(category, 1).first() {
+                    Some((name, score)) => lines.push(format!("  {}: {} ({})", category, name, show(category, *score))),
+                    None => lines.push(format!("  {}: nobody yet", category)),
+                }
+            }
+
+            return context.reply(&lines.join("\n"));
+        }
+    };
+
+    let ranked = context.stats.top(category, LEADERBOARD_SIZE);
+
+    if ranked.is_empty() {
+        return context.reply(&format!("Nobody is on the {} leaderboard yet.", category));
+    }
+
+    let mut lines = vec![format!("Top {} by {}:", ranked.len(), category)];
+
+    for (place, (name, score)) in ranked.iter().enumerate() {
+        lines.push(format!("  {}. {} - {}", place + 1, name, show(category, *score)));
+    }
+
+    context.reply(&lines.join("\n"))
+}
+
+/// Format a score for its category, time as hours and minutes
+fn show(category: Category, score: u64) -> String {
+    match category {
+        Category::Time => format!("{}h {}m {}s", score / 3600, score / 60 % 60, score % 60),
+        _ => score.to_string(),
+    }
+}
+
+fn export(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let caller = context.caller().name.clone();
+
+    if !context.config.is_admin(&args.join(" ")) {
+        return context.error(ErrorCode::Other, "Only admins can export statistics!");
+    }
+
+    let path = context.config.stats_export.clone();
+
+    match context.stats.export(&path) {
+        Ok(rows) => {
+            println!("[COMMAND]\t{} exported statistics for {} character(s) to {}", caller, rows, path);
+
+            context.reply(&format!("Exported statistics for {} character(s) to {}.", rows, path))
+        },
+        Err(reason) => {
+            eprintln!("[COMMAND]\tError: Could not export statistics: {}", reason);
+
+            context.error(ErrorCode::Other, "Could not export statistics!")
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::result;
+
+use crate::error_code::ErrorCode;
+use crate::utilities::send_character;
+
+use super::{Command, CommandContext, CommandRegistry};
+
+type Result<T> = result::Result<T, ()>;
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command { name: "trade", usage: "/trade [player]", help: "Ask a player in your room to trade, or show the current trade", handler: trade });
+    registry.register(Command { name: "offer", usage: "/offer <amount> gold | <item>", help: "Put gold or an item from your inventory into your trade", handler: offer });
+    registry.register(Command { name: "accept", usage: "/accept", help: "Agree to trade when asked, then to the offers; it goes through once both players accept", handler: accept });
+    registry.register(Command { name: "cancel", usage: "/cancel", help: "Refuse or call off your trade", handler: cancel });
+}
+
+fn trade(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let caller = context.caller().clone();
+
+    if args.is_empty() {
+        return match context.trades.find(&caller.name) {
+            Some(trade) => context.reply(&trade.describe()),
+            None => context.error(ErrorCode::Other, "You are not trading with anyone, try /trade <player>"),
+        };
+    }
+
+    let name = args.join(" ");
+
+    let partner = match context.characters.iter().find(|c| c.name == name && c.active) {
+        Some(partner) if partner.name == caller.name => return context.error(ErrorCode::NoTarget, "You cannot trade with yourself!"),
+        Some(partner) if partner.current_room != caller.current_room => return context.error(ErrorCode::NoTarget, &format!("{} is not in your room!", name)),
+        Some(partner) => partner.name.clone(),
+        None => return context.error(ErrorCode::NoTarget, &format!("There is no player named {} online!", name)),
+    };
+
+    if !context.trades.open(&caller.name, &partner) {
+        return context.error(ErrorCode::Other, &format!("You or {} are already trading, finish or /cancel it first!", partner));
+    }
+
+    println!("[COMMAND]\t{} asked {} to trade", caller.name, partner);
+
+    context.tell(&partner, &format!("{} wants to trade with you. Use /accept to start trading or /cancel to refuse.", caller.name))?;
+    context.reply(&format!("You asked {} to trade, waiting for them to agree.", partner))
+}
+
+fn offer(_registry: &CommandRegistry, context: &mut CommandContext, args: &[&str]) -> Result<()> {
+    let caller = context.caller().clone();
+
+    if args.is_empty() {
+        return context.error(ErrorCode::Other, "Usage: /offer <amount> gold | <item>");
+    }
+
+    let trade = match context.trades.find_mut(&caller.name) {
+        Some(trade) => trade,
+        None => return context.error(ErrorCode::Other, "You are not trading with anyone!"),
+    };
+
+    if !trade.agreed {
+        let asked = trade.parties[1].clone();
+
+        return context.error(ErrorCode::Other, &format!("{} has not agreed to trade yet!", asked));
+    }
+
+    // "/offer 10 gold" sets the gold offered, anything else is an item name
+    let offered = match (args.len(), args[0].parse::<u16>(), args.last()) {
+        (2, Ok(gold), Some(&"gold")) => trade.offer_gold(&caller, gold),
+        _ => trade.offer_item(&caller, &args.join(" ")),
+    };
+
+    let summary = trade.describe();
+    let partner = trade.partner(&caller.name).to_string();
+
+    if let Err(reason) = offered {
+        return context.error(ErrorCode::Other, &reason);
+    }
+
+    context.tell(&partner, &format!("{} changed their offer:\n{}", caller.name, summary))?;
+    context.reply(&summary)
+}
+
+fn accept(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let caller = context.caller().name.clone();
+
+    let trade = match context.trades.find_mut(&caller) {
+        Some(trade) => trade,
+        None => return context.error(ErrorCode::Other, "You are not trading with anyone!"),
+    };
+
+    let partner = trade.partner(&caller).to_string();
+
+    // Being asked to trade comes first, accepting it lets both sides start offering
+    if !trade.agreed {
+        if !trade.agree(&caller) {
+            return context.reply(&format!("You are still waiting for {} to agree to trade.", partner));
+        }
+
+        println!("[COMMAND]\t{} agreed to trade with {}", caller, partner);
+
+        context.tell(&partner, &format!("{} agreed to trade. Use /offer to put up gold or items and /accept when you are happy.", caller))?;
+        return context.reply(&format!("You are trading with {}. Use /offer to put up gold or items and /accept when you are happy.", partner));
+    }
+
+    if !trade.accept(&caller) {
+        let summary = trade.describe();
+
+        context.tell(&partner, &format!("{} accepted the trade, /accept to complete it:\n{}", caller, summary))?;
+        return context.reply(&format!("You accepted the trade, waiting on {}.", partner));
+    }
+
+    // Both sides agreed, the trade is over whether or not it can go through
+    let trade = context.trades.close(&caller).unwrap();
+
+    if let Err(reason) = trade.complete(context.characters) {
+        context.tell(&partner, &format!("The trade fell through: {}", reason))?;
+        return context.error(ErrorCode::Other, &format!("The trade fell through: {}", reason));
+    }
+
+    println!("[COMMAND]\tTrade completed between {} and {}", caller, partner);
+
+    for (side, name) in trade.parties.iter().enumerate() {
+        let character = context.characters.iter().find(|c| c.name == *name).unwrap();
+
+        context.tell(name, &format!("The trade is done!\n{}", trade.describe()))?;
+        send_character(&character.conn, character)?;
+
+        // Clients that track items see what left one inventory and arrived in the other
+        let receiver = trade.partner(name);
+
+        for item in trade.offers[side].items.iter() {
+            let item = match context.characters.iter().find(|c| c.name == receiver).and_then(|c| c.inventory.iter().find(|i| i.name == *item)) {
+                Some(item) => item.clone(),
+                None => continue,
+            };
+
+            let _ = context.send_item_to(name, &item, false, false);
+            let _ = context.send_item_to(receiver, &item, false, true);
+        }
+    }
+
+    Ok(())
+}
+
+fn cancel(_registry: &CommandRegistry, context: &mut CommandContext, _args: &[&str]) -> Result<()> {
+    let caller = context.caller().name.clone();
+
+    let trade = match context.trades.close(&caller) {
+        Some(trade) => trade,
+        None => return context.error(ErrorCode::Other, "You are not trading with anyone!"),
+    };
+
+    if trade.agreed {
+        context.tell(trade.partner(&caller), &format!("{} called off the trade.", caller))?;
+        return context.reply("You called off the trade.");
+    }
+
+    if trade.parties[0] == caller {
+        context.tell(trade.partner(&caller), &format!("{} took back their request to trade.", caller))?;
+        return context.reply("You took back your request to trade.");
+    }
+
+    context.tell(trade.partner(&caller), &format!("{} turned down the trade.", caller))?;
+    context.reply("You turned down the trade.")
+}
@@ -1,45 +1,263 @@
 use std::sync::{Arc, Mutex};
 use std::io::Write;
 use serde_json::Value;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use std::net::TcpStream;
 use std::result;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 use crate::message::Message;
 use crate::character::Character;
 use crate::error_code::ErrorCode;
-use crate::monster::Monster;
-
-use crate::utilities::{find_rooms, move_character, send_accept, send_character, send_connections, send_monster, send_room, send_player_update_to_room, send_monster_update_to_room};
+use crate::monster::{find_room_monster, spawn_monster, Monster};
+use crate::mailbox::{Mail, Mailbox};
+use crate::trade::Trades;
+use crate::party::{LootRule, Parties};
+use crate::npc::{find_room_npc, load_npcs, Conversations, Npc};
+use crate::quest::{advance, find_quest, load_quests, start_quest, Quest, QuestEvent, QuestReport};
+use crate::config::Config;
+use crate::shop::{load_shops, Shop};
+use crate::stats::StatsBook;
+use crate::death::DeathRules;
+use crate::effect::{enter_room, tick_room};
+use crate::event::{EventOutcome, Scheduler};
+use crate::exit::{room_id, visible_exits};
+use crate::boss::{find_boss, load_bosses, Boss, Encounters};
+use crate::combat::{combat_rules, resolve, CombatEvent, Fighter, Side};
+use crate::item::{find_item, load_items, place_item, room_items, Item, ITEM_EXTENSION};
+
+pub mod commands;
+
+use commands::{CommandContext, CommandRegistry};
+
+use crate::utilities::{announce, send_item, send_room_npcs, find_rooms, move_character, send_accept, send_character, send_connections, send_error, send_message, send_monster, send_narration, send_room, encode_extensions, parse_extensions, send_player_update_to_room, send_monster_update_to_room};
 
 type Result<T> = result::Result<T, ()>;
 
-pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut Value, active_monsters: &mut Vec<Monster>) -> Result<()> {
+/// How often timed game events such as respawning are checked
+const TICK: Duration = Duration::from_secs(1);
+
+/// How many ticks apart player statistics are saved
+const STATS_SAVE_TICKS: u64 = 30;
+
+/// How many teleport traps in a row one move can set off, so traps pointing at each other can't loop forever
+const MAX_TELEPORTS: u8 = 4;
+
+/// The LURK version this server speaks, sent in reply to every VERSION
+const MAJOR_REV: u8 = 2;
+const MINOR_REV: u8 = 3;
+
+/// Who a MESSAGE is delivered to, based on its recipient name; players may not use reserved names as character names
+#[derive(Debug, PartialEq)]
+pub enum Audience {
+    Player,     // A single character by name
+    Server,     // A slash command for the server itself
+    Room,       // Every character in the sender's room
+    Party,      // Every member of the sender's party
+    Everyone,   // Every active character on the server
+}
+
+impl From<&str> for Audience {
+    fn from(recipient: &str) -> Audience {
+        match recipient.to_lowercase().as_str() {
+            "server" => Audience::Server,
+            "room" => Audience::Room,
+            "party" => Audience::Party,
+            "all" | "everyone" => Audience::Everyone,
+            _ => Audience::Player,
+        }
+    }
+}
+
+pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut Value, active_monsters: &mut Vec<Monster>, seed: u64, config: Config) -> Result<()> {
     let mut characters: Vec<Character> = Vec::new();
-    
+    let mut mailbox = Mailbox::new();
+    let mut trades = Trades::new();
+    let mut parties = Parties::new();
+    let commands = CommandRegistry::builtin();
+    let items: Vec<Item> = load_items(map);
+    let mut item_clients: Vec<Arc<TcpStream>> = Vec::new();
+    let npcs: Vec<Npc> = load_npcs(map);
+    let mut conversations = Conversations::new();
+    let quests: Vec<Quest> = load_quests(map);
+    let shops: Vec<Shop> = load_shops(map, &items);
+    let rules = combat_rules(map["combat"].as_str().unwrap_or("classic"));
+    let death = DeathRules::from_map(map);
+    let bosses: Vec<Boss> = load_bosses(map);
+    let mut encounters = Encounters::new();
+    let mut scheduler = Scheduler::new(map);
+    let mut stats = StatsBook::load(&config.stats_path);
+    let mut last_tick = Instant::now();
+    let mut ticks: u64 = 0;
+
+    // Every random roll in the game comes from this so a session can be replayed from its seed
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    println!("[SERVER]\tLoaded {} items", items.len());
+    println!("[SERVER]\tLoaded {} NPCs", npcs.len());
+    println!("[SERVER]\tLoaded {} quests", quests.len());
+    println!("[SERVER]\tLoaded {} shops", shops.len());
+    println!("[SERVER]\tLoaded {} bosses", bosses.len());
+    println!("[SERVER]\tScheduled {} world events", scheduler.count());
+    println!("[SERVER]\tLoaded statistics for {} characters", stats.count());
+    println!("[SERVER]\tUsing {} combat rules", rules.name());
+    println!("[SERVER]\tDeath rules: {:?}", death);
+
+    // Commands and NPC conversations both work on the server's state through a CommandContext
+    macro_rules! command_context {
+        ($author:expr, $caller:expr) => {
+            CommandContext {
+                author: $author,
+                caller: $caller,
+                characters: &mut characters,
+                map,
+                monsters: active_monsters,
+                items: &items,
+                item_clients: &item_clients,
+                quests: &quests,
+                shops: &shops,
+                trades: &mut trades,
+                parties: &mut parties,
+                encounters: &mut encounters,
+                npcs: &npcs,
+                conversations: &mut conversations,
+                scheduler: &scheduler,
+                stats: &mut stats,
+                death: &death,
+                rng: &mut rng,
+                config: &config,
+            }
+        };
+    }
+
     loop {
         // Lock the message receiver
         let receiver = message_receiver.lock();
 
-        // Receive a message
-        let message = receiver.unwrap().recv().map_err(|err| {
-            // Disconnect all characters
-            for character in characters.iter_mut() {
-                character.active = false;
-                character.conn.as_ref().shutdown(std::net::Shutdown::Both).unwrap();
+        // Receive a message, waking up every tick even if nobody is talking
+        let message = match receiver.unwrap().recv_timeout(TICK) {
+            Ok(message) => Some(message),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(err) => {
+                // Disconnect all characters
+                for character in characters.iter_mut() {
+                    character.active = false;
+                    character.conn.as_ref().shutdown(std::net::Shutdown::Both).unwrap();
+                }
+
+                eprintln!("[SERVER]\tError: Could not receive message: {}\n", err);
+
+                std::process::exit(1);
+            }
+        };
+
+        // Run timed game events
+        if last_tick.elapsed() >= TICK {
+            last_tick = Instant::now();
+
+            ticks += 1;
+
+            respawn_characters(&death, &mut characters, map, active_monsters, &npcs);
+            apply_room_effects(&death, &mut stats, &mut characters, map, ticks);
+
+            for outcome in scheduler.run(ticks, map, active_monsters) {
+                report_event(&outcome, &characters, map, active_monsters);
             }
 
-            eprintln!("[SERVER]\tError: Could not receive message: {}\n", err);
+            for boss in encounters.abandon(&characters, active_monsters) {
+                println!("[SERVER]\tBoss {} was abandoned and recovered", boss);
+            }
+
+            if ticks.is_multiple_of(STATS_SAVE_TICKS) {
+                stats.save();
+            }
+        }
 
-            std::process::exit(1);
-        })?;
+        let message = match message {
+            Some(message) => message,
+            None => continue,
+        };
 
         match message {
-            Message::Message { author: _, message_type, message_len, recipient, sender, message } => {
+            Message::Message { author, message_type, message_len, recipient, sender, message } => {
                 println!("[SERVER]\tReceived message from: {}", sender);
                 println!("[SERVER]\tSending message to: {}", recipient);
 
                 println!("[SERVER]\tMessage:\n\t{}", message);
 
+                let audience = Audience::from(recipient.as_str());
+
+                // Run commands addressed to the server or starting with '/'
+                if audience == Audience::Server || message.starts_with('/') {
+                    let caller = match characters.iter().position(|c| Arc::ptr_eq(&c.conn, &author)) {
+                        Some(caller) => caller,
+                        None => {
+                            eprintln!("[SERVER]\tError: Could not find character who sent the command");
+                            continue;
+                        }
+                    };
+
+                    let mut context = command_context!(&author, caller);
+
+                    if commands.dispatch(&mut context, &message).is_err() {
+                        eprintln!("[SERVER]\tError: Could not run command: {}", message);
+                    }
+
+                    continue;
+                }
+
+                // Relay to the room or the whole server if the recipient is reserved
+
+                if audience != Audience::Player {
+                    let speaker = match characters.iter().find(|c| Arc::ptr_eq(&c.conn, &author)) {
+                        Some(speaker) => speaker,
+                        None => {
+                            eprintln!("[SERVER]\tError: Could not find character who sent the message");
+                            continue;
+                        }
+                    };
+
+                    if audience == Audience::Party && parties.find(&speaker.name).is_none() {
+                        let _ = send_error(&author, ErrorCode::NoTarget, "You are not in a party!");
+                        continue;
+                    }
+
+                    let party = parties.members(&speaker.name);
+
+                    let listeners = characters.iter().filter(|c| {
+                        c.active && !Arc::ptr_eq(&c.conn, &author) && match audience {
+                            Audience::Everyone => true,
+                            Audience::Party => party.contains(&c.name),
+                            _ => c.current_room == speaker.current_room,
+                        }
+                    });
+
+                    for listener in listeners {
+                        println!("[SERVER]\tRelaying message to: {}", listener.name);
+
+                        let _ = send_message(&listener.conn, &recipient, &speaker.name, &message, false);
+                    }
+
+                    continue;
+                }
+
+                // Talk to an NPC in the sender's room
+                if let Some(speaker) = characters.iter().position(|c| Arc::ptr_eq(&c.conn, &author)) {
+                    if let Some(npc) = find_room_npc(&npcs, characters[speaker].current_room as usize, &recipient) {
+                        send_accept(&author, 1).map_err(|_err| {
+                            eprintln!("[SERVER]\tError: Could not send accept message to character");
+                        })?;
+
+                        let mut context = command_context!(&author, speaker);
+
+                        talk_to_npc(&mut context, npc, &message);
+
+                        continue;
+                    }
+                }
+
                 let mut server_message: Vec<u8> = Vec::new();
 
                 // Resize the sender and recipient to 32 bytes
@@ -56,17 +274,44 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                 server_message.extend(message.as_bytes());
 
                 // Find the first recipient in the characters list
-                let recipient = characters.iter().find(|c| c.name == recipient);
-
-                match recipient {
-                    Some(recipient) => {
+                match characters.iter().find(|c| c.name == recipient) {
+                    Some(character) if character.active => {
                         // Send the message to the recipient
-                        recipient.conn.as_ref().write_all(&server_message).map_err(|err| {
+                        character.conn.as_ref().write_all(&server_message).map_err(|err| {
                             eprintln!("[SERVER]\tError: Could not send message to character: {}", err);
                         })?;
+
+                        send_accept(&author, 1).map_err(|_err| {
+                            eprintln!("[SERVER]\tError: Could not send accept message to character");
+                        })?;
+                    },
+                    Some(_character) => {
+                        println!("[SERVER]\tRecipient is offline, queueing message for: {}", recipient);
+
+                        let mail = Mail { sender: sender.clone(), recipient: recipient.clone(), message };
+
+                        if !mailbox.post(mail) {
+                            send_error(&author, ErrorCode::Other, &format!("{}'s mailbox is full!", recipient)).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send error message to character");
+                            })?;
+
+                            continue;
+                        }
+
+                        send_accept(&author, 1).map_err(|_err| {
+                            eprintln!("[SERVER]\tError: Could not send accept message to character");
+                        })?;
+
+                        send_narration(&author, &sender, &format!("{} is away; your message will be delivered when they return.", recipient)).map_err(|_err| {
+                            eprintln!("[SERVER]\tError: Could not send narration message to character");
+                        })?;
                     },
                     None => {
                         eprintln!("[SERVER]\tError: Could not find recipient to message");
+
+                        send_error(&author, ErrorCode::NoTarget, &format!("There is no character named {}!", recipient)).map_err(|_err| {
+                            eprintln!("[SERVER]\tError: Could not send error message to character");
+                        })?;
                     }
                 }
             },
@@ -110,19 +355,20 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     continue;
                 }
 
+                // A boss in the middle of a fight keeps everyone in the room
+                if let Some(boss) = encounters.lockdown(characters[index].current_room, active_monsters) {
+                    send_error(&author, ErrorCode::BadRoom, &format!("{} has sealed the way out! Defeat it or /flee.", boss.name)).map_err(|_err| {
+                        eprintln!("[SERVER]\tError: Could not send error message to character");
+                    })?;
+
+                    continue;
+                }
+
                 // Get exit names for the room and their ids
                 let old_room_num: usize = characters[index].current_room as usize;
 
-                let connection_names = match &map["rooms"][old_room_num]["exits"].as_array() {
-                    Some(exits) => {
-                        exits.iter().map(|e| e.as_str().unwrap()).collect::<Vec<&str>>()
-                    },
-                    None => {
-                        eprintln!("[SERVER]\tError: Could not get room exits");
-                        author.as_ref().shutdown(std::net::Shutdown::Read).unwrap_or_default();
-                        return Err(());
-                    }
-                };
+                let exits = visible_exits(map, old_room_num, &characters[index]);
+                let connection_names = exits.iter().map(|e| e.room.as_str()).collect::<Vec<&str>>();
 
                 // Get the connections for the room
                 let all_rooms = &map["rooms"].as_array();
@@ -171,23 +417,40 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     continue;
                 }
 
-                println!("[SERVER]\tMoving character to room: {}", room_num);
+                // Make sure nothing is stopping the character from using the exit
+                let exit = match exits.iter().find(|e| room_id(map, &e.room) == Some(room_num)) {
+                    Some(exit) => exit.clone(),
+                    None => continue,
+                };
 
-                // Move the character to the new room
-                move_character(map, &characters[index], room_num as usize, old_room_num);
+                if let Err(reason) = exit.check(&characters[index], active_monsters) {
+                    eprintln!("[SERVER]\tError: Exit to room {} is blocked: {}", room_num, reason);
 
-                // Update the characters room
-                characters[index].update_room(room_num);
+                    send_error(&author, ErrorCode::BadRoom, &reason).map_err(|_err| {
+                        eprintln!("[SERVER]\tError: Could not send error message to character");
+                    })?;
 
-                // Send the new room to the author
-                send_room(&author, &map, &characters, active_monsters, room_num as usize, old_room_num).map_err(|_err| {
-                    eprintln!("[SERVER]\tError: Could not send room message to character");
-                })?;
+                    continue;
+                }
 
-                // Send the connections to the author
-                send_connections(&author, &map, room_num as usize).map_err(|_err| {
-                    eprintln!("[SERVER]\tError: Could not send connections message to character");
-                })?;
+                if exit.toll > 0 {
+                    // Paid with checked subtraction so a toll can never wrap the character's gold
+                    if !characters[index].spend_gold(exit.toll) {
+                        send_error(&author, ErrorCode::BadRoom, &format!("You can't pay the {} gold toll to go to {}!", exit.toll, exit.room)).map_err(|_err| {
+                            eprintln!("[SERVER]\tError: Could not send error message to character");
+                        })?;
+
+                        continue;
+                    }
+
+                    let _ = send_narration(&author, &characters[index].name, &format!("You paid {} gold to go to {}.", exit.toll, exit.room));
+                }
+
+                println!("[SERVER]\tMoving character to room: {}", room_num);
+
+                let mut context = command_context!(&author, index);
+
+                enter(&mut context, index, room_num);
             },
             Message::Fight { author, message_type: _ } => {
                 println!("[SERVER]\tReceived fight message from: {:?}", author.peer_addr());
@@ -196,7 +459,6 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                 let initiator: &Character;
                 let current_room: usize;
                 let player_names: Vec<&str>;
-                let monster_names: Vec<&str>;
                 let mut players: Vec<&mut Character> = Vec::new();
                 let mut monsters: Vec<&mut Monster> = Vec::new();
 
@@ -238,6 +500,8 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
 
                 println!("[SERVER]\tFight Initiator: {}", initiator);
 
+                let party = parties.members(&initiator.name);
+
                 // Get the players in the room
                 match map["rooms"][current_room]["characters"].as_array() {
                     Some(players_array) => {
@@ -249,28 +513,19 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     }
                 }
 
+                // Only players who are actually here, so a stale name can't drag someone in from another room
                 players.extend(
                     characters.iter_mut()
                         .filter(
-                            |c| player_names.contains(&c.name.as_str())
+                            |c| c.active && c.current_room as usize == current_room && player_names.contains(&c.name.as_str())
                         )
                 );
 
                 // Get the monsters in the room
-                match &map["rooms"][current_room]["monsters"].as_array() {
-                    Some(monsters_array) => {
-                       monster_names = monsters_array.iter().map(|m| m.as_str().unwrap()).collect::<Vec<&str>>();
-                    },
-                    None => {
-                        eprintln!("[SERVER]\tError: Unable to get monsters in the room");
-                        continue;
-                    }
-                };
-
                 monsters.extend(
                     active_monsters.iter_mut()
                         .filter(
-                            |m| monster_names.contains(&m.name.as_str())
+                            |m| m.current_room as usize == current_room
                         )
                 );
 
@@ -296,197 +551,322 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     continue;
                 }
 
+                // The initiator and their party always fight, everyone else only if they set the join battle flag
+                let mut player_fighters = players.iter().map(|p| {
+                    let mut fighter = p.fighter();
+                    fighter.joins |= Arc::ptr_eq(&p.conn, &author) || party.contains(&p.name);
+                    fighter
+                }).collect::<Vec<Fighter>>();
+
                 // Log the players and monsters joining the fight
-                for player in players.iter() {
-                    println!("[SERVER]\tPlayer joining fight: {}", player.name);
+                for (player, fighter) in players.iter().zip(player_fighters.iter()) {
+                    if fighter.joins {
+                        println!("[SERVER]\tPlayer joining fight: {}", player.name);
+                    } else {
+                        println!("[SERVER]\tPlayer {} stays out of the fight", player.name);
+                    }
                 }
 
                 for monster in monsters.iter() {
                     println!("[SERVER]\tMonster joining fight: {}", monster.name);
                 }
 
-                /* 
-                    Fight logic
+                // Everyone fighting hears how the battle goes
+                let participants = players.iter()
+                    .zip(player_fighters.iter())
+                    .filter(|(_, f)| f.joins)
+                    .map(|(p, _)| (p.conn.clone(), p.name.clone()))
+                    .collect::<Vec<(Arc<TcpStream>, String)>>();
+
+                // Bosses remember how they started so they can recover if the players give up
+                for monster in monsters.iter().filter(|m| m.health > 0) {
+                    if let Some(boss) = find_boss(&bosses, &monster.kind) {
+                        encounters.engage(boss, monster);
+                    }
+                }
 
-                    Every player in the room will attack each monster
-                    Every monster in the room will attack each player
+                // Resolve the round with the map's combat rules
+                let mut monster_fighters = monsters.iter().map(|m| m.fighter()).collect::<Vec<Fighter>>();
 
-                    Total damage - player/monster defense = actual damage taken 
+                let events = resolve(rules.as_ref(), &mut player_fighters, &mut monster_fighters, &mut rng);
 
-                    Each player/monster regenerates health equal to 10% of their regen stat
-                */
+                // Items dropped and experience earned from monsters killed in this fight
+                let mut drops: Vec<(String, String)> = Vec::new();
+                let mut experience: u32 = 0;
+                let mut killed: Vec<String> = Vec::new();
 
-                // Pool player stats
-                let mut total_player_damage: i64 = 0;
+                for event in events {
+                    match event {
+                        CombatEvent::Hit { side: Side::Monsters, target, damage } => {
+                            let monster = &mut monsters[target];
 
-                players.iter().for_each(|player| {
-                    if (player.flags >> 6) & 1 == 0 {
-                        return;
-                    }
+                            println!("[SERVER]\tMonster: {} took {} damage", monster.name, damage);
 
-                    total_player_damage += player.attack as i64;
-                });
+                            for (conn, name) in participants.iter() {
+                                let _ = send_message(conn, name, "Server", &format!("The players are attacking {}!", monster.name), false);
+                            }
 
-                // Pool monster stats
-                let mut total_monster_damage: i64 = 0;
+                            monster.health = monster_fighters[target].health;
 
-                monsters.iter().for_each(|monster| {
-                    if (monster.flags >> 6) & 1 == 0 {
-                        return;
-                    }
+                            send_monster_update_to_room(&player_names, monster, &players_to_alert).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send monster update message to room");
+                            })?;
+                        },
+                        CombatEvent::Hit { side: Side::Players, target, damage } => {
+                            let player = &mut players[target];
 
-                    total_monster_damage += monster.attack as i64;
-                });
+                            println!("[SERVER]\tPlayer {} took {} damage", player.name, damage);
 
-                // Calculate monster health
-                for monster in monsters {
-                    let damage = total_player_damage - monster.defense as i64;
+                            for (conn, name) in participants.iter() {
+                                let _ = send_message(conn, name, "Server", &format!("The monsters are attacking {}!", player.name), false);
+                            }
 
-                    if damage <= 0 {
-                        println!("[SERVER]\tMonster: {} took no damage", monster.name);
-                        continue;
+                            player.health = player_fighters[target].health;
+
+                            send_player_update_to_room(&player_names, player, &players_to_alert).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send player update message to room");
+                            })?;
+                        },
+                        CombatEvent::Blocked { side: Side::Monsters, target } | CombatEvent::Missed { side: Side::Monsters, target } => {
+                            println!("[SERVER]\tMonster: {} took no damage", monsters[target].name);
+                        },
+                        CombatEvent::Blocked { side: Side::Players, target } | CombatEvent::Missed { side: Side::Players, target } => {
+                            println!("[SERVER]\tPlayer {} took no damage", players[target].name);
+                        },
+                        CombatEvent::Regen { side: Side::Monsters, target, amount } => {
+                            println!("[SERVER]\tMonster: {} regenerated {} health", monsters[target].name, amount);
+                        },
+                        CombatEvent::Regen { side: Side::Players, target, amount } => {
+                            println!("[SERVER]\tPlayer {} regenerated {} health", players[target].name, amount);
+                        },
+                        CombatEvent::Death { side: Side::Monsters, target } => {
+                            let monster = &mut monsters[target];
+
+                            println!("[SERVER]\tMonster: {} is dead", monster.name);
+
+                            // Mark the monster as dead via flags
+                            monster.flags = 0x38; // 00111000 = 0x38
+
+                            drops.extend(monster.drops.iter().map(|item| (monster.name.clone(), item.clone())));
+                            experience += monster.experience;
+                            killed.push(monster.kind.clone());
+
+                            send_monster_update_to_room(&player_names, monster, &players_to_alert).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send monster update message to room");
+                            })?;
+                        },
+                        CombatEvent::Death { side: Side::Players, target } => {
+                            let player = &mut players[target];
+
+                            println!("[SERVER]\tPlayer {} is dead", player.name);
+
+                            // Mark the player as dead via flags
+                            player.flags = 0x18; // 00011000 = 0x18
+
+                            stats.death(&player.name);
+
+                            let text = death.on_death(player);
+
+                            let _ = send_narration(&player.conn, &player.name, &text);
+
+                            send_player_update_to_room(&player_names, player, &players_to_alert).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send player update message to room");
+                            })?;
+                        },
                     }
+                }
 
-                    if (monster.flags >> 6) & 1 == 0 {
-                        println!("[SERVER]\tMonster: {} does not join fights", monster.name);
-                        continue;
-                    }
+                // Bosses use their abilities and change phase once the round is over
+                let mut summons: Vec<String> = Vec::new();
+
+                for monster in monsters.iter_mut() {
+                    let boss = match find_boss(&bosses, &monster.kind) {
+                        Some(boss) => boss,
+                        None => continue,
+                    };
 
                     if monster.health <= 0 {
-                        println!("[SERVER]\tMonster: {} is already dead", monster.name);
+                        if encounters.end(monster.id) && boss.lockdown {
+                            for (conn, name) in participants.iter() {
+                                let _ = send_narration(conn, name, &format!("With {} defeated, the way out is clear.", monster.name));
+                            }
+                        }
+
                         continue;
                     }
 
-                    // Send Narration message to the author
-                    let mut message: Vec<u8> = Vec::new();
-
-                    let mut r_bytes = monster.name.bytes().collect::<Vec<u8>>();
-                    let mut s_bytes = "Server".bytes().collect::<Vec<u8>>();
-
-                    r_bytes.resize(32, 0);
-                    s_bytes.resize(32, 0);
-
-                    let narration = format!("The players are attacking {}!", monster.name).as_bytes().to_vec();
-                    let message_len = narration.len() as u16;
+                    let turn = encounters.round(boss, monster);
 
-                    message.push(1);
-                    message.extend(message_len.to_le_bytes());
-                    message.extend(r_bytes);
-                    message.extend(s_bytes);
-                    message.extend(narration);
+                    for text in turn.narration.iter() {
+                        for (conn, name) in participants.iter() {
+                            let _ = send_narration(conn, name, text);
+                        }
+                    }
 
-                    // Send the narration message to the author
-                    author.as_ref().write_all(&message).map_err(|err| {
-                        eprintln!("[SERVER]\tError: Could not send narration message to character: {}", err);
-                    })?;
+                    summons.extend(turn.summon);
 
-                    println!("[SERVER]\tMonster: {} took {} damage", monster.name, damage as i16);
+                    if turn.damage > 0 {
+                        for (player, _) in players.iter_mut().zip(player_fighters.iter()).filter(|(p, f)| f.joins && p.health > 0) {
+                            println!("[SERVER]\tPlayer {} took {} damage from {}", player.name, turn.damage, monster.name);
 
-                    monster.health -= damage as i16;
+                            player.health = player.health.saturating_sub(turn.damage);
 
-                    // Regenerate health
-                    let regen = monster.regen as f64 * 0.10;
+                            if player.health <= 0 {
+                                println!("[SERVER]\tPlayer {} is dead", player.name);
 
-                    println!("[SERVER]\tMonster: {} regenerated {} health", monster.name, regen as i16);
+                                player.flags = 0x18; // 00011000 = 0x18
 
-                    monster.health += regen as i16;
+                                stats.death(&player.name);
 
-                    // Check if the monster is dead
-                    if monster.health <= 0 {
-                        println!("[SERVER]\tMonster: {} is dead", monster.name);
+                                let text = death.on_death(player);
 
-                        // Mark the monster as dead via flags
-                        monster.flags = 0x38; // 00111000 = 0x38
+                                let _ = send_narration(&player.conn, &player.name, &text);
+                            }
 
-                        // Remove the monster's attack from the total damage
-                        total_monster_damage -= monster.attack as i64;
-                        
-                        println!("[SERVER]\tRemoved monster damage {}, remaining: {}", monster.attack as i64, total_monster_damage);
+                            send_player_update_to_room(&player_names, player, &players_to_alert).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send player update message to room");
+                            })?;
+                        }
                     }
 
-                    println!("[SERVER]\tSending monster update to room");
-
-                    // Send the updated monster to the author
                     send_monster_update_to_room(&player_names, monster, &players_to_alert).map_err(|_err| {
                         eprintln!("[SERVER]\tError: Could not send monster update message to room");
                     })?;
                 }
 
-                // Calculate player health
-                for player in players {
-                    let damage = total_monster_damage - player.defense as i64;
+                // Award experience to every living player who joined the fight
+                if experience > 0 {
+                    for (player, _) in players.iter_mut().zip(player_fighters.iter()).filter(|(p, f)| f.joins && p.health > 0) {
+                        let levels = player.gain_experience(experience);
 
-                    if damage <= 0 {
-                        println!("[SERVER]\tPlayer {} took no damage", player.name);
-                        continue;
+                        println!("[SERVER]\tPlayer {} gained {} experience and {} level(s)", player.name, experience, levels);
+
+                        let _ = send_narration(&player.conn, &player.name, &format!("You gained {} experience!", experience));
+
+                        if levels > 0 {
+                            let _ = send_narration(&player.conn, &player.name, &format!(
+                                "You reached level {}! You have {} stat point(s) to spend; send your character again with higher stats to use them.",
+                                player.level, player.stat_points
+                            ));
+                        }
                     }
+                }
 
-                    if (player.flags >> 6) & 1 == 0 {
-                        println!("[SERVER]\tPlayer {} does not join fights", player.name);
-                        continue;
+                // Every player who joined the fight gets credit for the kills
+                for kind in killed.iter() {
+                    for (player, _) in players.iter_mut().zip(player_fighters.iter()).filter(|(_, f)| f.joins) {
+                        stats.kill(&player.name);
+
+                        let report = advance(&quests, &items, player, &QuestEvent::Killed(kind));
+
+                        report_quests(player, &report, &item_clients);
                     }
+                }
 
-                    if player.health <= 0 {
-                        println!("[SERVER]\tPlayer {} is already dead", player.name);
-                        continue;
+                // Monsters that died leave their items on the floor
+                for (monster, item) in drops {
+                    println!("[SERVER]\tMonster: {} dropped {}", monster, item);
+
+                    place_item(map, current_room, &item);
+
+                    for player in players_to_alert.iter().filter(|c| c.active && c.current_room as usize == current_room) {
+                        let _ = send_narration(&player.conn, &player.name, &format!("{} dropped a {}!", monster, item));
                     }
+                }
 
-                    // Send Narration message to the author
-                    let mut message: Vec<u8> = Vec::new();
+                // Monsters a boss called in arrive after the round
+                for kind in summons {
+                    let name = match spawn_monster(map, active_monsters, &kind, current_room) {
+                        Some(name) => name,
+                        None => {
+                            eprintln!("[SERVER]\tError: Boss tried to summon unknown monster: {}", kind);
+                            continue;
+                        }
+                    };
 
-                    let mut r_bytes = player.name.bytes().collect::<Vec<u8>>();
-                    let mut s_bytes = "Server".bytes().collect::<Vec<u8>>();
+                    println!("[SERVER]\tMonster: {} was summoned into room {}", name, current_room);
 
-                    r_bytes.resize(32, 0);
-                    s_bytes.resize(32, 0);
+                    let summoned = active_monsters.last().unwrap();
 
-                    let narration = format!("The monsters are attacking {}!", player.name).as_bytes().to_vec();
-                    let message_len = narration.len() as u16;
+                    for player in players_to_alert.iter().filter(|c| c.active && c.current_room as usize == current_room) {
+                        let _ = send_monster(&player.conn, summoned);
+                        let _ = send_narration(&player.conn, &player.name, &format!("{} joins the fight!", name));
+                    }
+                }
+            },
+            Message::Loot { author, message_type: _, target_name } => {
+                println!("[SERVER]\tReceived loot message from: {:?}", author.peer_addr());
+                println!("[SERVER]\tAttempting to loot target: {}", target_name);
 
-                    message.push(1);
-                    message.extend(message_len.to_le_bytes());
-                    message.extend(r_bytes);
-                    message.extend(s_bytes);
-                    message.extend(narration);
+                let current_room = match characters.iter().find(|c| Arc::ptr_eq(&c.conn, &author)) {
+                    Some(initiator) => initiator.current_room as usize,
+                    None => {
+                        eprintln!("[SERVER]\tError: Could not find initiator of loot message!");
+                        continue;
+                    }
+                };
 
-                    // Send the narration message to the author
-                    author.as_ref().write_all(&message).map_err(|err| {
-                        eprintln!("[SERVER]\tError: Could not send narration message to character: {}", err);
-                    })?;
+                // Dead players can be looted as well as monsters
+                if find_room_monster(active_monsters, current_room, &target_name).is_none() {
+                    if let Some(target) = characters.iter().position(|c| c.name == target_name && !Arc::ptr_eq(&c.conn, &author)) {
+                        let initiator = match characters.iter().position(|c| Arc::ptr_eq(&c.conn, &author)) {
+                            Some(initiator) => initiator,
+                            None => continue,
+                        };
+
+                        if !config.player_loot {
+                            let _ = send_error(&author, ErrorCode::NoPlayerCombat, "Looting other players is not allowed!");
+                            continue;
+                        }
 
-                    println!("[SERVER]\tPlayer {} took {} damage", player.name, damage);
+                        if characters[target].current_room as usize != current_room {
+                            let _ = send_error(&author, ErrorCode::NoTarget, &format!("{} is not in this room!", target_name));
+                            continue;
+                        }
 
-                    player.health -= damage as i16;
+                        if characters[initiator].health <= 0 {
+                            let _ = send_error(&author, ErrorCode::Other, "Player is dead and cannot loot!");
+                            continue;
+                        }
 
-                    // Regenerate health
-                    let regen = player.regen as f64 * 0.10;
+                        if !characters[target].lootable() {
+                            let _ = send_error(&author, ErrorCode::NoTarget, &format!("{} is not dead and cannot be looted!", target_name));
+                            continue;
+                        }
 
-                    println!("[SERVER]\tPlayer {} regenerated {} health", player.name, regen);
+                        if characters[target].gold == 0 {
+                            let _ = send_error(&author, ErrorCode::NoTarget, &format!("{} has already been looted!", target_name));
+                            continue;
+                        }
 
-                    player.health += regen as i16;
+                        let gold = characters[target].gold;
 
-                    // Check if the player is dead
-                    if player.health <= 0 {
-                        println!("[SERVER]\tPlayer {} is dead", player.name);
+                        println!("[SERVER]\tPlayer: {} looted Player: {} in room {} for {} gold!", characters[initiator].name, target_name, current_room, gold);
 
-                        // Mark the player as dead via flags
-                        player.flags = 0x18; // 00011000 = 0x18
+                        characters[initiator].add_gold(gold);
+                        characters[target].gold = 0;
 
-                        // Remove the player's attack from the total damage
-                        total_player_damage -= player.attack as i64;
-                    }
+                        stats.gold(&characters[initiator].name, gold);
 
-                    println!("[SERVER]\tSending player update to room");
+                        // Let everyone in the room see both characters change
+                        let player_names = match map["rooms"][current_room]["characters"].as_array() {
+                            Some(players) => players.iter().filter_map(|p| p.as_str()).collect::<Vec<&str>>(),
+                            None => Vec::new(),
+                        };
 
-                    // Send the updated player to the author
-                    send_player_update_to_room(&player_names, player, &players_to_alert).map_err(|_err| {
-                        eprintln!("[SERVER]\tError: Could not send player update message to room");
-                    })?;
-                }    
-            },
-            Message::Loot { author, message_type: _, target_name } => {
-                println!("[SERVER]\tReceived loot message from: {:?}", author.peer_addr());
-                println!("[SERVER]\tAttempting to loot target: {}", target_name);
+                        for character in [&characters[initiator], &characters[target]] {
+                            send_player_update_to_room(&player_names, character, &characters).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send player update message to room");
+                            })?;
+                        }
+
+                        if characters[target].active {
+                            let _ = send_narration(&characters[target].conn, &target_name, &format!("{} looted {} gold from your body!", characters[initiator].name, gold));
+                        }
+
+                        continue;
+                    }
+                }
 
                 // Get character of the author
                 let initiator = match characters.iter_mut().find(|c| Arc::ptr_eq(&c.conn, &author)) {
@@ -496,12 +876,9 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                         continue;
                     }
                 };
-                
-                // Get the current room
-                let current_room = initiator.current_room as usize;
 
                 // Check if the target is in the room
-                let target = match active_monsters.iter_mut().find(|c| c.name == target_name) {
+                let target = match find_room_monster(active_monsters, current_room, &target_name) {
                     Some(target) => target,
                     None => {
                         eprintln!("[SERVER]\tError: Could not find target to loot");
@@ -593,27 +970,55 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
 
                 println!("[SERVER]\tPlayer: {} looted Monster: {} in room {} for {} gold!", initiator.name, target.name, current_room, target.gold);
 
-                // Adjust stats for the initiator and target
-                initiator.gold += target.gold;
-                target.gold = 0;
+                let gold = target.gold;
+                let looter = initiator.name.clone();
 
-                // Send the updated player to the author
-                send_character(&author, &initiator).map_err(|_err| {
-                    eprintln!("[SERVER]\tError: Could not send character message to character");
-                })?;
+                target.gold = 0;
 
                 // Send the updated monster to the author
-                send_monster(&author, &target).map_err(|_err| {
+                send_monster(&author, target).map_err(|_err| {
                     eprintln!("[SERVER]\tError: Could not send monster message to character");
                 })?;
+
+                // A party splitting its loot shares the gold with the members in the room
+                let sharers = match parties.find(&looter) {
+                    Some(party) if party.loot == LootRule::Split => characters.iter()
+                        .filter(|c| c.active && c.current_room as usize == current_room && party.contains(&c.name))
+                        .map(|c| c.name.clone())
+                        .collect::<Vec<String>>(),
+                    _ => vec![looter.clone()],
+                };
+
+                let share = gold / sharers.len() as u16;
+
+                for character in characters.iter_mut().filter(|c| sharers.contains(&c.name)) {
+                    let amount = if character.name == looter { gold - share * (sharers.len() as u16 - 1) } else { share };
+
+                    character.add_gold(amount);
+
+                    stats.gold(&character.name, amount);
+
+                    if character.name != looter {
+                        let _ = send_narration(&character.conn, &character.name, &format!("{} looted {} and gave you {} gold as your share.", looter, target_name, amount));
+                    }
+
+                    // Send the updated player to them
+                    send_character(&character.conn, character).map_err(|_err| {
+                        eprintln!("[SERVER]\tError: Could not send character message to character");
+                    })?;
+                }
             }, 
             Message::Start { author, message_type: _ } => {
                 println!("[SERVER]\tReceived start message from: {:?}", author.peer_addr());
 
-                send_room(&author, &map, &characters, active_monsters, 0, 0).map_err(|_err| {
+                send_room(&author, map, &characters, active_monsters, 0, 0).map_err(|_err| {
                     eprintln!("[SERVER]\tError: Could not send room message to character");
                 })?;
 
+                send_room_npcs(&author, &npcs, 0).map_err(|_err| {
+                    eprintln!("[SERVER]\tError: Could not send NPCs to character");
+                })?;
+
                 // Send the character to the author
                 let character = match characters.iter_mut().find(|c| Arc::ptr_eq(&c.conn, &author)) {
                     Some(character) => character,
@@ -645,12 +1050,19 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     continue;
                 }
 */
-                // Update the character flags to show that the character has started
-                character.flags = 0xd8;
+                // Update the character flags to show that the character has started, keeping their join battle choice
+                if character.health > 0 {
+                    character.flags = 0x98 | (character.flags & 0x40); // 0xd8 = 11011000 when joining battles
+                } else {
+                    character.flags = 0x18 | (character.flags & 0x40); // Dead characters wait to respawn
+                }
 
                 println!("[SERVER]\tCharacter started: {}", character);
 
-                send_connections(&author, &map, 0).map_err(|_err| {
+                character.visit(character.current_room);
+                stats.visit(&character.name, character.current_room);
+
+                send_connections(&author, map, 0, character).map_err(|_err| {
                     eprintln!("[SERVER]\tError: Could not send connections message to character");
                 })?;
             },
@@ -673,6 +1085,58 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
             Message::Character { author, message_type: _, name, flags, attack, defense, regen, health, gold, current_room, description_len: _, description } => {
                 println!("[SERVER]\tReceived character message from: {:?}", author.peer_addr());
 
+                // Reserved recipients and NPC names cannot be used as character names
+                if Audience::from(name.as_str()) != Audience::Player || npcs.iter().any(|n| n.name.eq_ignore_ascii_case(&name)) {
+                    eprintln!("[SERVER]\tError: Character name is reserved: {}", name);
+
+                    send_error(&author, ErrorCode::PlayerExists, "That name is reserved by the server!").map_err(|_err| {
+                        eprintln!("[SERVER]\tError: Could not send error message to character");
+                    })?;
+
+                    continue;
+                }
+
+                // A started character re-submitting itself is spending the stat points it has earned
+                if let Some(index) = characters.iter().position(|c| c.name == name && c.active && Arc::ptr_eq(&c.conn, &author) && (c.flags >> 4) & 1 == 1) {
+                    match characters[index].spend_points(attack, defense, regen) {
+                        Ok(spent) => {
+                            println!("[SERVER]\tCharacter {} spent {} stat points", name, spent);
+
+                            send_accept(&author, 10).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send accept message to character");
+                            })?;
+
+                            send_character(&author, &characters[index]).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send character message to character");
+                            })?;
+
+                            send_narration(&author, &name, &format!("You spent {} stat point(s), {} remaining.", spent, characters[index].stat_points)).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send narration message to character");
+                            })?;
+                        },
+                        Err(reason) => {
+                            eprintln!("[SERVER]\tError: Character {} could not spend stat points: {}", name, reason);
+
+                            send_error(&author, ErrorCode::StatError, reason).map_err(|_err| {
+                                eprintln!("[SERVER]\tError: Could not send error message to character");
+                            })?;
+                        }
+                    }
+
+                    continue;
+                }
+
+                // A connection that has started playing may only re-submit its own character
+                if characters.iter().any(|c| c.active && Arc::ptr_eq(&c.conn, &author) && (c.flags >> 4) & 1 == 1) {
+                    eprintln!("[SERVER]\tError: Connection already plays a started character, refusing: {}", name);
+
+                    send_error(&author, ErrorCode::PlayerExists, "You are already playing a character!").map_err(|_err| {
+                        eprintln!("[SERVER]\tError: Could not send error message to character");
+                    })?;
+
+                    continue;
+                }
+
                 // Locate the character in the list
                 let mut index = 0;
                 let mut found = false;
@@ -683,6 +1147,19 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     found = true;
                 }
 
+                // Permanently dead characters are gone for good, so their name starts a new character
+                if found && death.frees_name(&characters[index]) {
+                    println!("[SERVER]\tReplacing permanently dead character: {}", name);
+
+                    let old = characters.remove(index);
+
+                    if let Some(room) = map["rooms"][old.current_room as usize]["characters"].as_array_mut() {
+                        room.retain(|c| c.as_str() != Some(old.name.as_str()));
+                    }
+
+                    found = false;
+                }
+
                 // Check if the character is already in the list
                 if found {
                     println!("[SERVER]\tCharacter already exists: {}", name);
@@ -708,46 +1185,28 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                         continue;
                     }
 
-                    println!("[SERVER]\tSending narration message to: {}", name);
-
-                    // Send narration message to the author
-                    let mut server_message: Vec<u8> = Vec::new();
-                    let mut r_bytes = characters[index].name.bytes().collect::<Vec<u8>>();
-                    let mut s_bytes = "Narrator".bytes().collect::<Vec<u8>>();
-
-                    r_bytes.resize(32, 0);
-                    s_bytes.resize(32, 0);
-
-                    // Narration marker
-                    s_bytes[30] = 0;
-                    s_bytes[31] = 1;
-
-                    // Get the starting room
-                    let starting_room = map["rooms"][0]["name"].as_str().unwrap_or("Temple Entrance");
-
-                    let message_len: u16 = if starting_room == "Temple Entrance" { 84 } else { 143 };
-
-                    // Send the message to the author
-                    server_message.push(1);
-                    server_message.extend(message_len.to_le_bytes());
-                    server_message.extend(r_bytes);
-                    server_message.extend(s_bytes);
+                    // Bring the character back into the world, dead characters still have to wait to respawn
+                    characters[index].active = true;
 
-                    if starting_room == "Temple Entrance" {
-                        server_message.extend(b"As you regain conciousness, you see a Wallmaster retreating into the darkness above.");
+                    if characters[index].health > 0 {
+                        characters[index].flags = 0x88 | (flags & 0x40); // 0xc8 = 11001000 when joining battles
                     } else {
-                        server_message.extend(b"You feel exhasted and groggy, you hear laughing and the sound of wood clacking together. A Skullkid must have dragged you back to the entrance.");
+                        characters[index].flags = 0x08 | (flags & 0x40);
                     }
 
-                    // Bring the character back to life
-                    characters[index].active = true;
-                    characters[index].flags = 0xc8; // 0xc8 = 11001000
                     characters[index].update_connection(author.clone());
+
+                    // Keep the room's list of characters in step with where the character comes back
+                    let old_room_num = characters[index].current_room as usize;
+
+                    move_character(map, &characters[index], current_room as usize, old_room_num);
                     characters[index].update_room(current_room);
 
                     println!("[SERVER]\tAccepted character: {}", characters[index]);
 
-                    send_accept(&author).map_err(|_err| {
+                    stats.start_session(&name);
+
+                    send_accept(&author, 10).map_err(|_err| {
                         eprintln!("[SERVER]\tError: Could not send accept message to character");
                     })?;
 
@@ -755,10 +1214,27 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     send_character(&author, &characters[index]).map_err(|_err| {
                         eprintln!("[SERVER]\tError: Could not send character message to character");
                     })?;
-              
-                    author.as_ref().write_all(&server_message).map_err(|err| {
-                        eprintln!("[SERVER]\tError: Could not send message to character: {}", err);
-                    })?;
+
+                    if let Some(respawn_at) = characters[index].respawn_at {
+                        let wait = respawn_at.saturating_duration_since(Instant::now()).as_secs();
+
+                        let _ = send_narration(&author, &name, &format!("You are still dead. You will return in {} second(s).", wait));
+                    }
+
+                    // Deliver any messages that arrived while the character was away
+                    let waiting = mailbox.take(&name);
+
+                    if !waiting.is_empty() {
+                        let _ = send_narration(&author, &name, &format!("You have {} message(s) waiting for you.", waiting.len()));
+                    }
+
+                    for mail in waiting {
+                        send_message(&author, &mail.recipient, &mail.sender, &mail.message, false).map_err(|_err| {
+                            eprintln!("[SERVER]\tError: Could not deliver queued message to character");
+                        })?;
+                    }
+
+                    announce(&characters, &format!("{} has returned to the world.", name));
 
                     continue;
                 } 
@@ -775,7 +1251,18 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     health,
                     gold,
                     current_room,
-                    description: String::from_utf8_lossy(&description).to_string()
+                    description: String::from_utf8_lossy(&description).to_string(),
+                    inventory: Vec::new(),
+                    weapon: None,
+                    armor: None,
+                    experience: 0,
+                    level: 1,
+                    stat_points: 0,
+                    respawn_at: None,
+                    discovered: Vec::new(),
+                    claimed: Vec::new(),
+                    quests: Vec::new(),
+                    visited: Vec::new(),
                 };
 
                 println!("[SERVER]\tCharacter added: {}", character);
@@ -786,15 +1273,28 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                 // Add the character to the list
                 characters.push(character);
 
+                stats.start_session(&name);
+
                 // Accept the character
-                send_accept(&author).map_err(|_err| {
+                send_accept(&author, 10).map_err(|_err| {
                     eprintln!("[SERVER]\tError: Could not send accept message to character");
                 })?;
 
                 // Send the character to the author
-                send_character(&author, &characters[index]).map_err(|_err| {
+                send_character(&author, &characters[characters.len() - 1]).map_err(|_err| {
                     eprintln!("[SERVER]\tError: Could not send character message to character");
                 })?;
+
+                announce(&characters, &format!("{} has entered the world.", name));
+
+                // Hand out the quests everyone starts with
+                let character = characters.len() - 1;
+
+                for quest in quests.iter().filter(|q| q.automatic) {
+                    start_quest(quest, &mut characters[character]);
+
+                    let _ = send_narration(&author, &name, &format!("New quest: {} - {}", quest.name, quest.description));
+                }
             },
             Message::Game { author, message_type, initial_points, stat_limit, description_len, description } => {
                 match author.as_ref().peer_addr() {
@@ -854,12 +1354,35 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     Some(character) => {
                         character.active = false;
                         character.flags = 0x00; // 0x00 = 00000000 Dead, Inactive, and Not in game
+
+                        let name = character.name.clone();
+                        let text = format!("{} has left the world.", name);
+
+                        stats.end_session(&name);
+                        stats.save();
+
+                        if let Some(remaining) = parties.leave(&name) {
+                            for member in characters.iter().filter(|c| c.active && remaining.contains(&c.name)) {
+                                let _ = send_narration(&member.conn, &member.name, &format!("{} left the party.", name));
+                            }
+                        }
+
+                        // Call off any trade they were in the middle of
+                        if let Some(trade) = trades.close(&name) {
+                            if let Some(partner) = characters.iter().find(|c| c.name == trade.partner(&name) && c.active) {
+                                let _ = send_narration(&partner.conn, &partner.name, &format!("{} left, the trade is off.", name));
+                            }
+                        }
+
+                        announce(&characters, &text);
                     },
                     None => {
                         eprintln!("[SERVER]\tError: Could not find character to deactivate");
                     }
                 };
 
+                item_clients.retain(|c| !Arc::ptr_eq(c, &author));
+
                 // Disconnect the client
                 match author.shutdown(std::net::Shutdown::Both) {
                     Ok(_) => {
@@ -870,10 +1393,10 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     }
                 }
             },
-            Message::Version { author, message_type, major_rev, minor_rev, extension_len: _, extensions: _ } => {
+            Message::Version { author, message_type, major_rev, minor_rev, extension_len: _, extensions } => {
                 match author.as_ref().peer_addr() {
                     Ok(addr) => {
-                        println!("[SERVER]\tReceived version message from: {:?}", addr);
+                        println!("[SERVER]\tReceived version {}.{} message from: {:?}", major_rev, minor_rev, addr);
                     },
                     Err(err) => {
                         eprintln!("[SERVER]\tError: Could not get address of author: {}", err);
@@ -892,12 +1415,22 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
                     }
                 }
 
+                // Remember clients that understand the extensions we support
+                for extension in parse_extensions(&extensions) {
+                    if extension == ITEM_EXTENSION && !item_clients.iter().any(|c| Arc::ptr_eq(c, &author)) {
+                        println!("[SERVER]\tClient supports extension: {}", extension);
+                        item_clients.push(author.clone());
+                    }
+                }
+
+                let supported = encode_extensions(&[ITEM_EXTENSION]);
                 let mut message: Vec<u8> = Vec::new();
 
                 message.push(message_type);
-                message.extend(major_rev.to_le_bytes());
-                message.extend(minor_rev.to_le_bytes());
-                message.extend(0u16.to_le_bytes());
+                message.push(MAJOR_REV);
+                message.push(MINOR_REV);
+                message.extend((supported.len() as u16).to_le_bytes());
+                message.extend(supported);
 
                 // Send the version to the author
                 author.as_ref().write_all(&message).map_err(|err| {
@@ -916,3 +1449,351 @@ pub fn handle_server(message_receiver: Arc<Mutex<Receiver<Message>>>, map: &mut
         }
     }
 }  
+
+/// Bring back every dead character whose respawn timer has run out
+fn respawn_characters(death: &DeathRules, characters: &mut [Character], map: &mut Value, active_monsters: &mut [Monster], npcs: &[Npc]) {
+    for index in 0..characters.len() {
+        if !death.respawn_due(&characters[index]) {
+            continue;
+        }
+
+        let old_room = characters[index].current_room as usize;
+        let penalty = death.respawn(map, &mut characters[index]);
+        let character = &characters[index];
+
+        println!("[SERVER]\tCharacter {} respawned in room {} and lost {} gold", character.name, character.current_room, penalty);
+
+        if !character.active {
+            continue;
+        }
+
+        // Sending the room also shows everyone who saw them die, and everyone where they wake up
+        let _ = send_room(&character.conn, map, characters, active_monsters, character.current_room as usize, old_room);
+        let _ = send_room_npcs(&character.conn, npcs, character.current_room as usize);
+        let _ = send_connections(&character.conn, map, character.current_room as usize, character);
+        let _ = send_narration(&character.conn, &character.name, &death.narration);
+
+        if penalty > 0 {
+            let _ = send_narration(&character.conn, &character.name, &format!("You lost {} gold.", penalty));
+        }
+    }
+}
+
+/// Hurt or heal everyone standing in a room with effects that repeat over time
+fn apply_room_effects(death: &DeathRules, stats: &mut StatsBook, characters: &mut [Character], map: &Value, tick: u64) {
+    for index in 0..characters.len() {
+        if !characters[index].active || characters[index].health <= 0 {
+            continue;
+        }
+
+        let room_num = characters[index].current_room as usize;
+        let messages = tick_room(map, room_num, &mut characters[index], tick);
+
+        if messages.is_empty() {
+            continue;
+        }
+
+        let character = &mut characters[index];
+
+        for text in messages.iter() {
+            let _ = send_narration(&character.conn, &character.name, text);
+        }
+
+        hurt_by_room(death, stats, character);
+
+        let character = characters[index].clone();
+
+        let player_names = match map["rooms"][room_num]["characters"].as_array() {
+            Some(players) => players.iter().filter_map(|p| p.as_str()).collect::<Vec<&str>>(),
+            None => Vec::new(),
+        };
+
+        let _ = send_player_update_to_room(&player_names, &character, characters);
+    }
+}
+
+/// Tell players about a world event and show the rooms it changed to anyone standing in them
+fn report_event(outcome: &EventOutcome, characters: &[Character], map: &Value, active_monsters: &mut [Monster]) {
+    for character in characters.iter().filter(|c| c.active) {
+        let room_num = character.current_room as usize;
+        let here = outcome.rooms.contains(&room_num);
+
+        if !outcome.narration.is_empty() && (outcome.everyone || here) {
+            let _ = send_narration(&character.conn, &character.name, &outcome.narration);
+        }
+
+        if here {
+            let _ = send_room(&character.conn, map, characters, active_monsters, room_num, room_num);
+        }
+    }
+}
+
+/// Kill a character whose health a room effect took to nothing
+fn hurt_by_room(death: &DeathRules, stats: &mut StatsBook, character: &mut Character) {
+    if character.health > 0 || (character.flags >> 7) & 1 == 0 {
+        return;
+    }
+
+    println!("[SERVER]\tPlayer {} was killed by their surroundings", character.name);
+
+    character.flags = 0x18; // 00011000 = 0x18
+
+    stats.death(&character.name);
+
+    let text = death.on_death(character);
+
+    let _ = send_narration(&character.conn, &character.name, &text);
+}
+
+/// Walk a character into a room through the same steps as every move: the room lists, the room, its NPCs and exits,
+/// ending any conversation, the floor, quest progress and the room's effects. Teleport traps send the character on
+/// through the same steps, up to `MAX_TELEPORTS` times. Sending is best effort, so a dead connection only costs that
+/// character their updates.
+fn enter(context: &mut CommandContext, index: usize, room_num: u16) {
+    let conn = context.characters[index].conn.clone();
+    let name = context.characters[index].name.clone();
+    let mut room_num = room_num;
+    let mut old_room_num = context.characters[index].current_room as usize;
+    let mut teleports = 0;
+
+    loop {
+        move_character(context.map, &context.characters[index], room_num as usize, old_room_num);
+
+        context.characters[index].update_room(room_num);
+        context.stats.visit(&name, room_num);
+
+        let _ = send_room(&conn, context.map, context.characters, context.monsters, room_num as usize, old_room_num);
+        let _ = send_room_npcs(&conn, context.npcs, room_num as usize);
+
+        // Walking away ends any conversation
+        context.conversations.end(&name);
+
+        let _ = send_connections(&conn, context.map, room_num as usize, &context.characters[index]);
+
+        // Point out anything lying on the floor
+        let floor = room_items(context.map, room_num as usize);
+
+        if !floor.is_empty() {
+            let _ = send_narration(&conn, &name, &format!("On the ground you see: {}", floor.join(", ")));
+        }
+
+        let report = advance(context.quests, context.items, &mut context.characters[index], &QuestEvent::Reached(room_num));
+
+        report_quests(&context.characters[index], &report, context.item_clients);
+
+        // Hazards hit as soon as the character walks in
+        let effects = enter_room(context.map, room_num as usize, &mut context.characters[index], context.rng);
+
+        for text in effects.narration.iter() {
+            let _ = send_narration(&conn, &name, text);
+        }
+
+        hurt_by_room(context.death, context.stats, &mut context.characters[index]);
+
+        let _ = send_character(&conn, &context.characters[index]);
+
+        let (destination, narration) = match effects.teleport {
+            Some(teleport) => teleport,
+            None => break,
+        };
+
+        if (destination as usize) >= context.map["rooms"].as_array().map_or(0, |rooms| rooms.len()) {
+            eprintln!("[SERVER]\tError: Room {} teleports to room {} which doesn't exist", room_num, destination);
+            break;
+        }
+
+        if teleports >= MAX_TELEPORTS || context.characters[index].health <= 0 || context.encounters.lockdown(room_num, context.monsters).is_some() {
+            break;
+        }
+
+        println!("[SERVER]\tCharacter {} was teleported from room {} to room {}", name, room_num, destination);
+
+        if !narration.is_empty() {
+            let _ = send_narration(&conn, &name, &narration);
+        }
+
+        teleports += 1;
+        old_room_num = room_num as usize;
+        room_num = destination;
+    }
+}
+
+/// Carry on a conversation with an NPC, handing over anything the reached node gives the first time
+fn talk_to_npc(context: &mut CommandContext, npc: &Npc, said: &str) {
+    let conversations = &mut *context.conversations;
+    let item_clients = context.item_clients;
+    let item_client = item_clients.iter().any(|c| Arc::ptr_eq(c, context.author));
+    let (map, items, quests) = (&*context.map, context.items, context.quests);
+    let character = &mut context.characters[context.caller];
+
+    let node = match conversations.talk(npc, &character.name, said) {
+        Some(node) => node,
+        None => {
+            let _ = send_message(&character.conn, &character.name, &npc.name, "Farewell.", false);
+            return;
+        }
+    };
+
+    let dialogue = &npc.nodes[&node];
+
+    println!("[SERVER]\t{} is talking to {} at node '{}'", character.name, npc.name, node);
+
+    let _ = send_message(&character.conn, &character.name, &npc.name, &dialogue.speech(), false);
+
+    let report = advance(quests, items, character, &QuestEvent::Talked(&npc.name));
+
+    report_quests(character, &report, item_clients);
+
+    if let Some(quest) = dialogue.start_quest.as_ref().and_then(|id| find_quest(quests, id)) {
+        if start_quest(quest, character) {
+            let _ = send_narration(&character.conn, &character.name, &format!("New quest: {} - {}", quest.name, quest.description));
+        }
+    }
+
+    let reward = (npc.name.clone(), node);
+
+    if character.claimed.contains(&reward) {
+        return;
+    }
+
+    character.claimed.push(reward);
+
+    if let Some(item) = dialogue.give_item.as_ref().and_then(|name| find_item(items, name)) {
+        let _ = send_narration(&character.conn, &character.name, &format!("{} gave you a {}!", npc.name, item.name));
+
+        if item_client {
+            let _ = send_item(&character.conn, &item, false, true);
+        }
+
+        character.inventory.push(item);
+    }
+
+    if dialogue.give_gold > 0 {
+        character.add_gold(dialogue.give_gold);
+
+        let _ = send_narration(&character.conn, &character.name, &format!("{} gave you {} gold!", npc.name, dialogue.give_gold));
+        let _ = send_character(&character.conn, character);
+    }
+
+    if let Some(exit) = &dialogue.unlock {
+        if !character.discovered(npc.current_room, exit) {
+            character.discovered.push((npc.current_room, exit.clone()));
+        }
+
+        let _ = send_narration(&character.conn, &character.name, &format!("The way to {} is open to you now.", exit));
+
+        if character.current_room == npc.current_room {
+            let _ = send_connections(&character.conn, map, npc.current_room as usize, character);
+        }
+    }
+}
+
+/// Tell a character how their quests moved along, and show them any rewards they received
+/// along with ITEM updates for the items that came and went if their client understands them
+fn report_quests(character: &Character, report: &QuestReport, item_clients: &[Arc<TcpStream>]) {
+    if report.messages.is_empty() {
+        return;
+    }
+
+    for message in report.messages.iter() {
+        let _ = send_narration(&character.conn, &character.name, message);
+    }
+
+    if item_clients.iter().any(|c| Arc::ptr_eq(c, &character.conn)) {
+        for item in report.delivered.iter() {
+            let _ = send_item(&character.conn, item, false, false);
+        }
+
+        for item in report.received.iter() {
+            let _ = send_item(&character.conn, item, false, true);
+        }
+    }
+
+    let _ = send_character(&character.conn, character);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::character::tests::character;
+    use crate::server_thread::commands::tests::{contains, run_in};
+
+    fn map() -> Value {
+        json!({ "rooms": [
+            { "id": 0, "name": "Start", "description": "","characters": ["Alice"] },
+            { "id": 1, "name": "Trap", "description": "","characters": [], "effects": [{ "type": "teleport", "room": 2, "narration": "Whoosh!" }] },
+            { "id": 2, "name": "Loop", "description": "","characters": [], "effects": [{ "type": "teleport", "room": 1, "narration": "Whoosh!" }] },
+            { "id": 3, "name": "Pit", "description": "","characters": [], "effects": [
+                { "type": "damage", "amount": 100, "narration": "Spikes!" },
+                { "type": "teleport", "room": 0, "narration": "Whoosh!" },
+            ] },
+            { "id": 4, "name": "Cave", "description": "","characters": ["Bob"], "effects": [{ "type": "darkness", "light": "Torch" }] },
+        ] })
+    }
+
+    /// Bob waiting in the cave on a connection that stays open, so alerting him works
+    fn bob() -> (Character, TcpStream) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut bob = character("Bob");
+        bob.conn = Arc::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+        bob.current_room = 4;
+
+        (bob, listener.accept().unwrap().0)
+    }
+
+    fn occupants(map: &Value, room_num: usize) -> Vec<&str> {
+        map["rooms"][room_num]["characters"].as_array().unwrap().iter().filter_map(|c| c.as_str()).collect()
+    }
+
+    #[test]
+    fn teleport_loops_stop_after_the_limit() {
+        let mut map = map();
+        let mut characters = vec![character("Alice")];
+
+        let sent = run_in(&mut map, &mut characters, |context| enter(context, 0, 1));
+        let teleports = String::from_utf8_lossy(&sent).matches("Whoosh!").count();
+
+        assert_eq!(teleports, MAX_TELEPORTS as usize);
+        assert_eq!(characters[0].current_room, 1);
+        assert_eq!(occupants(&map, 1), vec!["Alice"]);
+        assert!(occupants(&map, 0).is_empty());
+        assert!(occupants(&map, 2).is_empty());
+    }
+
+    #[test]
+    fn the_dead_are_not_teleported() {
+        let mut map = map();
+        let mut characters = vec![character("Alice")];
+
+        let sent = run_in(&mut map, &mut characters, |context| enter(context, 0, 3));
+
+        assert!(contains(&sent, "Spikes!"));
+        assert!(!contains(&sent, "Whoosh!"));
+        assert!(characters[0].health <= 0);
+        assert_eq!(characters[0].current_room, 3);
+        assert_eq!(occupants(&map, 3), vec!["Alice"]);
+    }
+
+    #[test]
+    fn dark_rooms_hide_who_is_there() {
+        let mut world = map();
+        let (waiting, _peer) = bob();
+        let mut characters = vec![character("Alice"), waiting];
+
+        let sent = run_in(&mut world, &mut characters, |context| enter(context, 0, 4));
+
+        assert!(contains(&sent, "Alice"));
+        assert!(!contains(&sent, "Bob"));
+
+        let mut world = map();
+        let (waiting, _peer) = bob();
+        let mut characters = vec![character("Alice"), waiting];
+        characters[0].inventory.push(crate::item::Item::from_json(&json!({ "name": "Torch", "kind": "key" })).unwrap());
+
+        let sent = run_in(&mut world, &mut characters, |context| enter(context, 0, 4));
+
+        assert!(contains(&sent, "Bob"));
+    }
+}
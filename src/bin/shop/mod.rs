@@ -0,0 +1,191 @@
+use serde_json::Value;
+
+use crate::item::{find_item, Item};
+use crate::monster::Monster;
+
+/// Something a shop sells
+#[derive(Debug, Clone)]
+pub struct Listing {
+    pub item: String,
+    pub price: u16,
+    pub apply: bool, // Used up on the spot as a stat boost instead of going into the inventory
+}
+
+/// A shopkeeper's stall from the map's "shops" list
+#[derive(Debug, Clone)]
+pub struct Shop {
+    pub keeper: String, // Monster or NPC running the shop, a monster keeper closes it when killed
+    pub room: u16,
+    pub stock: Vec<Listing>,
+    pub buy_rate: u8,   // Percent of an item's value paid when a player sells it
+}
+
+impl Shop {
+    pub fn from_json(shop: &Value, items: &[Item]) -> Option<Shop> {
+        let keeper = shop["keeper"].as_str()?.to_string();
+
+        let stock = match shop["stock"].as_array() {
+            Some(stock) => stock.iter().filter_map(|listing| {
+                let item = listing["item"].as_str()?;
+
+                // Only sell what is in the item catalog, using its spelling of the name
+                let item = match find_item(items, item) {
+                    Some(item) => item.name,
+                    None => {
+                        eprintln!("[SHOP]\t\tError: {} sells unknown item '{}'", keeper, item);
+                        return None;
+                    }
+                };
+
+                Some(Listing {
+                    item,
+                    price: listing["price"].as_u64()?.min(u16::MAX as u64) as u16,
+                    apply: listing["apply"].as_bool().unwrap_or(false),
+                })
+            }).collect(),
+            None => Vec::new(),
+        };
+
+        Some(Shop {
+            keeper,
+            room: shop["room"].as_u64()? as u16,
+            stock,
+            buy_rate: shop["buy_rate"].as_u64().unwrap_or(50).min(100) as u8,
+        })
+    }
+
+    /// Find a listing by item name
+    pub fn listing(&self, name: &str) -> Option<&Listing> {
+        self.stock.iter().find(|l| l.item.eq_ignore_ascii_case(name))
+    }
+
+    /// Gold the shop pays for an item, or None if it won't buy it
+    pub fn offer(&self, item: &Item) -> Option<u16> {
+        let offer = (item.value as u32 * self.buy_rate as u32 / 100) as u16;
+
+        if offer == 0 {
+            return None;
+        }
+
+        Some(offer)
+    }
+
+    /// Whether the keeper is still around to trade; a monster keeper has to be alive
+    pub fn is_open(&self, monsters: &[Monster]) -> bool {
+        let keepers = monsters.iter()
+            .filter(|m| m.current_room == self.room && (m.kind == self.keeper || m.name == self.keeper))
+            .collect::<Vec<&Monster>>();
+
+        keepers.is_empty() || keepers.iter().any(|m| m.health > 0)
+    }
+}
+
+/// Load the shops from the map
+pub fn load_shops(map: &Value, items: &[Item]) -> Vec<Shop> {
+    match map["shops"].as_array() {
+        Some(shops) => shops.iter().filter_map(|s| Shop::from_json(s, items)).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Find the shop in a room
+pub fn find_shop(shops: &[Shop], room_num: usize) -> Option<&Shop> {
+    shops.iter().find(|s| s.room as usize == room_num)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::character::tests::character;
+    use crate::item::load_items;
+
+    fn items() -> Vec<Item> {
+        load_items(&json!({
+            "items": [
+                { "name": "Deku Shield", "kind": "armor", "defense": 5, "value": 40 },
+                { "name": "Red Potion", "kind": "consumable", "health": 20, "value": 1 },
+            ]
+        }))
+    }
+
+    fn shop(buy_rate: u64) -> Shop {
+        Shop::from_json(&json!({
+            "keeper": "Deku Scrub",
+            "room": 2,
+            "buy_rate": buy_rate,
+            "stock": [
+                { "item": "deku shield", "price": 50 },
+                { "item": "Red Potion", "price": 100000, "apply": true },
+                { "item": "Master Sword", "price": 1 },
+            ]
+        }), &items()).unwrap()
+    }
+
+    #[test]
+    fn stock_comes_from_the_item_catalog() {
+        let shop = shop(50);
+
+        assert_eq!(shop.stock.len(), 2);
+        assert_eq!(shop.listing("DEKU SHIELD").unwrap().item, "Deku Shield");
+        assert_eq!(shop.listing("Red Potion").unwrap().price, u16::MAX);
+        assert!(shop.listing("Red Potion").unwrap().apply);
+        assert!(shop.listing("Master Sword").is_none());
+        assert_eq!(Shop::from_json(&json!({ "keeper": "Deku Scrub", "room": 2, "buy_rate": 250 }), &[]).unwrap().buy_rate, 100);
+    }
+
+    #[test]
+    fn offers_round_down_and_refuse_worthless_items() {
+        let items = items();
+
+        assert_eq!(shop(50).offer(&items[0]), Some(20));
+        assert_eq!(shop(33).offer(&items[0]), Some(13));
+        assert_eq!(shop(100).offer(&items[0]), Some(40));
+        assert_eq!(shop(0).offer(&items[0]), None);
+        assert_eq!(shop(50).offer(&items[1]), None);
+    }
+
+    #[test]
+    fn buying_needs_enough_gold() {
+        let price = shop(50).listing("Deku Shield").unwrap().price;
+        let mut link = character("Link");
+
+        link.gold = price - 1;
+        assert!(!link.spend_gold(price));
+        assert_eq!(link.gold, price - 1);
+
+        link.gold = price;
+        assert!(link.spend_gold(price));
+        assert_eq!(link.gold, 0);
+    }
+
+    #[test]
+    fn selling_saturates_gold() {
+        let offer = shop(100).offer(&items()[0]).unwrap();
+        let mut link = character("Link");
+
+        link.gold = u16::MAX - 10;
+        link.add_gold(offer);
+
+        assert_eq!(link.gold, u16::MAX);
+    }
+
+    #[test]
+    fn shops_close_when_a_monster_keeper_dies() {
+        let shop = shop(50);
+        let mut scrub = Monster::new(String::from("Deku Scrub"), String::new());
+        scrub.current_room = 2;
+        scrub.health = 5;
+
+        assert!(shop.is_open(&[]));
+        assert!(shop.is_open(std::slice::from_ref(&scrub)));
+
+        scrub.health = 0;
+        assert!(!shop.is_open(std::slice::from_ref(&scrub)));
+
+        scrub.current_room = 3;
+        assert!(shop.is_open(&[scrub]));
+        assert_eq!(find_shop(std::slice::from_ref(&shop), 2).unwrap().keeper, "Deku Scrub");
+        assert!(find_shop(&[shop], 3).is_none());
+    }
+}
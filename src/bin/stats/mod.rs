@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+/// Everything a character has done since they first joined
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub kills: u32,
+    pub deaths: u32,
+    pub gold_earned: u32,
+    pub rooms: Vec<u16>,        // Every room they have set foot in
+    pub seconds_played: u64,
+}
+
+/// What a leaderboard ranks characters by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Kills,
+    Deaths,
+    Gold,
+    Rooms,
+    Time,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [Category::Kills, Category::Deaths, Category::Gold, Category::Rooms, Category::Time];
+
+    pub fn from_name(name: &str) -> Option<Category> {
+        match name.to_lowercase().as_str() {
+            "kills" => Some(Category::Kills),
+            "deaths" => Some(Category::Deaths),
+            "gold" => Some(Category::Gold),
+            "rooms" => Some(Category::Rooms),
+            "time" => Some(Category::Time),
+            _ => None,
+        }
+    }
+
+    fn score(&self, stats: &Stats) -> u64 {
+        match self {
+            Category::Kills => stats.kills as u64,
+            Category::Deaths => stats.deaths as u64,
+            Category::Gold => stats.gold_earned as u64,
+            Category::Rooms => stats.rooms.len() as u64,
+            Category::Time => stats.seconds_played,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Category::Kills => write!(f, "kills"),
+            Category::Deaths => write!(f, "deaths"),
+            Category::Gold => write!(f, "gold"),
+            Category::Rooms => write!(f, "rooms"),
+            Category::Time => write!(f, "time"),
+        }
+    }
+}
+
+/// Every character's statistics, kept on disk between server runs
+#[derive(Debug)]
+pub struct StatsBook {
+    stats: HashMap<String, Stats>,
+    sessions: HashMap<String, Instant>,    // When each online character last had their time counted
+    path: String,
+    dirty: bool,
+}
+
+impl StatsBook {
+    /// Load the statistics saved at the path, starting fresh if there are none
+    pub fn load(path: &str) -> StatsBook {
+        let stats = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                eprintln!("[STATS]\t\tError: Could not parse {}, starting fresh: {}", path, err);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        StatsBook { stats, sessions: HashMap::new(), path: path.to_string(), dirty: false }
+    }
+
+    /// How many characters have statistics
+    pub fn count(&self) -> usize {
+        self.stats.len()
+    }
+
+    fn entry(&mut self, name: &str) -> &mut Stats {
+        self.dirty = true;
+        self.stats.entry(name.to_string()).or_default()
+    }
+
+    pub fn kill(&mut self, name: &str) {
+        self.entry(name).kills += 1;
+    }
+
+    pub fn death(&mut self, name: &str) {
+        self.entry(name).deaths += 1;
+    }
+
+    pub fn gold(&mut self, name: &str, amount: u16) {
+        let stats = self.entry(name);
+        stats.gold_earned = stats.gold_earned.saturating_add(amount as u32);
+    }
+
+    /// Remember that the character has been in a room
+    pub fn visit(&mut self, name: &str, room: u16) {
+        if self.stats.get(name).is_some_and(|s| s.rooms.contains(&room)) {
+            return;
+        }
+
+        self.entry(name).rooms.push(room);
+    }
+
+    /// Start counting the character's time played
+    pub fn start_session(&mut self, name: &str) {
+        self.entry(name);
+        self.sessions.insert(name.to_string(), Instant::now());
+    }
+
+    /// Stop counting the character's time played
+    pub fn end_session(&mut self, name: &str) {
+        if let Some(started) = self.sessions.remove(name) {
+            self.entry(name).seconds_played += started.elapsed().as_secs();
+        }
+    }
+
+    /// Add the time online characters have played so far to their totals
+    fn count_sessions(&mut self) {
+        for (name, started) in self.sessions.iter_mut() {
+            let seconds = started.elapsed().as_secs();
+
+            if seconds == 0 {
+                continue;
+            }
+
+            // Only whole seconds are counted, the rest carries over
+            *started += Duration::from_secs(seconds);
+
+            self.stats.entry(name.clone()).or_default().seconds_played += seconds;
+            self.dirty = true;
+        }
+    }
+
+    /// The best characters in a category, highest first
+    pub fn top(&mut self, category: Category, limit: usize) -> Vec<(String, u64)> {
+        self.count_sessions();
+
+        let mut ranked = self.stats.iter()
+            .map(|(name, stats)| (name.clone(), category.score(stats)))
+            .filter(|(_, score)| *score > 0)
+            .collect::<Vec<(String, u64)>>();
+
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+
+        ranked
+    }
+
+    /// Write the statistics to disk if anything changed since the last save
+    pub fn save(&mut self) {
+        self.count_sessions();
+
+        if !self.dirty {
+            return;
+        }
+
+        match serde_json::to_string_pretty(&self.stats) {
+            Ok(contents) => match fs::write(&self.path, contents) {
+                Ok(_) => self.dirty = false,
+                Err(err) => eprintln!("[STATS]\t\tError: Could not write {}: {}", self.path, err),
+            },
+            Err(err) => eprintln!("[STATS]\t\tError: Could not serialize statistics: {}", err),
+        }
+    }
+
+    /// Write every character's statistics to a CSV file, returning how many rows were written
+    pub fn export(&mut self, path: &str) -> Result<usize, String> {
+        self.count_sessions();
+
+        let mut names = self.stats.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+
+        let mut file = File::create(path).map_err(|err| format!("Could not create {}: {}", path, err))?;
+
+        writeln!(file, "name,kills,deaths,gold_earned,rooms_discovered,seconds_played").map_err(|err| err.to_string())?;
+
+        for name in names.iter() {
+            let stats = &self.stats[name];
+
+            writeln!(file, "{},{},{},{},{},{}", name.replace(',', " "), stats.kills, stats.deaths, stats.gold_earned, stats.rooms.len(), stats.seconds_played)
+                .map_err(|err| err.to_string())?;
+        }
+
+        Ok(names.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("lurk-stats-{}-{}", std::process::id(), name)).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn counts_what_characters_do() {
+        let mut book = StatsBook::load(&temp_path("missing.json"));
+
+        book.kill("Alice");
+        book.kill("Alice");
+        book.death("Alice");
+        book.gold("Alice", 30);
+        book.gold("Alice", u16::MAX);
+        book.visit("Alice", 1);
+        book.visit("Alice", 1);
+        book.visit("Alice", 2);
+
+        let stats = &book.stats["Alice"];
+
+        assert_eq!(book.count(), 1);
+        assert_eq!(stats.kills, 2);
+        assert_eq!(stats.deaths, 1);
+        assert_eq!(stats.gold_earned, 30 + u16::MAX as u32);
+        assert_eq!(stats.rooms, vec![1, 2]);
+    }
+
+    #[test]
+    fn top_ranks_highest_first() {
+        let mut book = StatsBook::load(&temp_path("missing.json"));
+
+        book.kill("Bob");
+        book.kill("Carol");
+        book.kill("Carol");
+        book.kill("Alice");
+        book.death("Dave");
+
+        assert_eq!(book.top(Category::Kills, 10), vec![("Carol".to_string(), 2), ("Alice".to_string(), 1), ("Bob".to_string(), 1)]);
+        assert_eq!(book.top(Category::Kills, 1), vec![("Carol".to_string(), 2)]);
+        assert_eq!(book.top(Category::Deaths, 10), vec![("Dave".to_string(), 1)]);
+        assert!(book.top(Category::Gold, 10).is_empty());
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let path = temp_path("saved.json");
+        let mut book = StatsBook::load(&path);
+
+        book.kill("Alice");
+        book.visit("Alice", 4);
+        book.save();
+
+        let mut loaded = StatsBook::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.count(), 1);
+        assert_eq!(loaded.stats["Alice"].kills, 1);
+        assert_eq!(loaded.stats["Alice"].rooms, vec![4]);
+        assert_eq!(loaded.top(Category::Rooms, 10), vec![("Alice".to_string(), 1)]);
+    }
+
+    #[test]
+    fn exports_csv() {
+        let path = temp_path("export.csv");
+        let mut book = StatsBook::load(&temp_path("missing.json"));
+
+        book.kill("Smith, John");
+        book.gold("Alice", 5);
+
+        assert_eq!(book.export(&path), Ok(2));
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, "name,kills,deaths,gold_earned,rooms_discovered,seconds_played\nAlice,0,0,5,0,0\nSmith  John,1,0,0,0,0\n");
+    }
+}
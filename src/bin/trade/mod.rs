@@ -0,0 +1,350 @@
+use crate::character::Character;
+
+/// What one side of a trade puts up
+#[derive(Debug, Clone, Default)]
+pub struct Offer {
+    pub gold: u16,
+    pub items: Vec<String>,
+    pub accepted: bool,
+}
+
+impl Offer {
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+
+        if self.gold > 0 {
+            parts.push(format!("{} gold", self.gold));
+        }
+
+        parts.extend(self.items.iter().cloned());
+
+        if parts.is_empty() {
+            return String::from("nothing");
+        }
+
+        parts.join(", ")
+    }
+}
+
+/// A pending trade between two characters; nothing changes hands until both accept
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub parties: [String; 2], // The character who asked first, then the one they asked
+    pub offers: [Offer; 2],
+    pub agreed: bool,         // The character asked has agreed to trade, no offers until they do
+}
+
+impl Trade {
+    /// Index of the character's side of the trade
+    pub fn side(&self, name: &str) -> usize {
+        if self.parties[0] == name { 0 } else { 1 }
+    }
+
+    /// The character on the other side of the trade
+    pub fn partner(&self, name: &str) -> &str {
+        &self.parties[1 - self.side(name)]
+    }
+
+    /// Let the character who was asked agree to trade, returning whether they were the one asked
+    pub fn agree(&mut self, name: &str) -> bool {
+        if self.parties[1] != name {
+            return false;
+        }
+
+        self.agreed = true;
+
+        true
+    }
+
+    /// Change what a character offers, checking they can cover it; any change has to be accepted again
+    pub fn offer_gold(&mut self, character: &Character, gold: u16) -> Result<(), String> {
+        if character.gold < gold {
+            return Err(format!("You only have {} gold!", character.gold));
+        }
+
+        self.offers[self.side(&character.name)].gold = gold;
+        self.reset();
+
+        Ok(())
+    }
+
+    pub fn offer_item(&mut self, character: &Character, item: &str) -> Result<(), String> {
+        let side = self.side(&character.name);
+
+        let offered = self.offers[side].items.iter().filter(|i| i.eq_ignore_ascii_case(item)).count();
+        let carried = character.inventory.iter().filter(|i| i.name.eq_ignore_ascii_case(item)).count();
+
+        if carried <= offered {
+            return Err(format!("You are not carrying another {} (unequip it first)!", item));
+        }
+
+        let name = character.inventory.iter().find(|i| i.name.eq_ignore_ascii_case(item)).unwrap().name.clone();
+
+        self.offers[side].items.push(name);
+        self.reset();
+
+        Ok(())
+    }
+
+    /// Mark a character as happy with the trade, returning whether both sides now are
+    pub fn accept(&mut self, name: &str) -> bool {
+        let side = self.side(name);
+
+        self.offers[side].accepted = true;
+
+        self.offers.iter().all(|o| o.accepted)
+    }
+
+    fn reset(&mut self) {
+        for offer in self.offers.iter_mut() {
+            offer.accepted = false;
+        }
+    }
+
+    /// Both sides of the trade for showing to the players
+    pub fn describe(&self) -> String {
+        self.parties.iter().zip(self.offers.iter())
+            .map(|(name, offer)| {
+                let accepted = if offer.accepted { " (accepted)" } else { "" };
+
+                format!("{} offers: {}{}", name, offer.describe(), accepted)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Swap everything offered, checking both characters can still go through with it first so
+    /// either the whole trade happens or none of it does
+    pub fn complete(&self, characters: &mut [Character]) -> Result<(), String> {
+        let mut indexes = [0; 2];
+
+        for (side, name) in self.parties.iter().enumerate() {
+            indexes[side] = match characters.iter().position(|c| c.name == *name && c.active) {
+                Some(index) => index,
+                None => return Err(format!("{} is no longer here!", name)),
+            };
+        }
+
+        let [first, second] = indexes;
+
+        if characters[first].current_room != characters[second].current_room {
+            return Err(String::from("You must be in the same room to trade!"));
+        }
+
+        for side in 0..2 {
+            let giver = &characters[indexes[side]];
+            let receiver = &characters[indexes[1 - side]];
+            let offer = &self.offers[side];
+
+            if giver.gold < offer.gold {
+                return Err(format!("{} no longer has {} gold!", giver.name, offer.gold));
+            }
+
+            let received = (receiver.gold as u32).saturating_sub(self.offers[1 - side].gold as u32) + offer.gold as u32;
+
+            if received > u16::MAX as u32 {
+                return Err(format!("{} cannot carry that much gold!", receiver.name));
+            }
+
+            for item in offer.items.iter() {
+                let offered = offer.items.iter().filter(|i| *i == item).count();
+                let carried = giver.inventory.iter().filter(|i| i.name == *item).count();
+
+                if carried < offered {
+                    return Err(format!("{} no longer has the {}!", giver.name, item));
+                }
+            }
+        }
+
+        // Take everything from both sides before handing anything over
+        let mut taken = [Vec::new(), Vec::new()];
+
+        for side in 0..2 {
+            let giver = &mut characters[indexes[side]];
+
+            giver.spend_gold(self.offers[side].gold);
+
+            for item in self.offers[side].items.iter() {
+                taken[side].extend(giver.take_item(item));
+            }
+        }
+
+        for side in 0..2 {
+            let receiver = &mut characters[indexes[1 - side]];
+
+            receiver.add_gold(self.offers[side].gold);
+            receiver.inventory.append(&mut taken[side]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Every trade in progress, each character can be in at most one
+#[derive(Debug, Default)]
+pub struct Trades {
+    open: Vec<Trade>,
+}
+
+impl Trades {
+    pub fn new() -> Trades {
+        Trades { open: Vec::new() }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Trade> {
+        self.open.iter().find(|t| t.parties.iter().any(|p| p == name))
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Trade> {
+        self.open.iter_mut().find(|t| t.parties.iter().any(|p| p == name))
+    }
+
+    /// Ask for a trade between two characters, returning false if either is already trading.
+    /// The second character has to agree before anything can be offered.
+    pub fn open(&mut self, first: &str, second: &str) -> bool {
+        if self.find(first).is_some() || self.find(second).is_some() {
+            return false;
+        }
+
+        self.open.push(Trade {
+            parties: [first.to_string(), second.to_string()],
+            offers: [Offer::default(), Offer::default()],
+            agreed: false,
+        });
+
+        true
+    }
+
+    /// Remove the character's trade, returning it
+    pub fn close(&mut self, name: &str) -> Option<Trade> {
+        let index = self.open.iter().position(|t| t.parties.iter().any(|p| p == name))?;
+
+        Some(self.open.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::character::tests::character;
+    use crate::item::Item;
+
+    fn item(name: &str) -> Item {
+        Item::from_json(&json!({ "name": name, "kind": "consumable" })).unwrap()
+    }
+
+    /// Alice and Bob in a trade both have agreed to, Alice carrying a potion and 20 gold
+    fn setup() -> (Trades, Vec<Character>) {
+        let mut alice = character("Alice");
+        alice.gold = 20;
+        alice.inventory.push(item("Red Potion"));
+
+        let mut trades = Trades::new();
+
+        assert!(trades.open("Alice", "Bob"));
+        assert!(trades.find_mut("Bob").unwrap().agree("Bob"));
+
+        (trades, vec![alice, character("Bob")])
+    }
+
+    #[test]
+    fn only_the_character_asked_can_agree() {
+        let mut trades = Trades::new();
+
+        trades.open("Alice", "Bob");
+
+        let trade = trades.find_mut("Alice").unwrap();
+
+        assert!(!trade.agree("Alice"));
+        assert!(!trade.agreed);
+        assert!(trade.agree("Bob"));
+    }
+
+    #[test]
+    fn characters_trade_with_one_partner_at_a_time() {
+        let (mut trades, _) = setup();
+
+        assert!(!trades.open("Carol", "Alice"));
+        assert!(!trades.open("Bob", "Carol"));
+
+        assert!(trades.close("Bob").is_some());
+        assert!(trades.open("Carol", "Alice"));
+    }
+
+    #[test]
+    fn offers_must_be_covered() {
+        let (mut trades, characters) = setup();
+        let trade = trades.find_mut("Alice").unwrap();
+
+        assert!(trade.offer_gold(&characters[0], 21).is_err());
+        assert!(trade.offer_item(&characters[0], "red potion").is_ok());
+        assert!(trade.offer_item(&characters[0], "red potion").is_err());
+        assert_eq!(trade.offers[0].items, vec!["Red Potion"]);
+    }
+
+    #[test]
+    fn changing_an_offer_takes_back_acceptance() {
+        let (mut trades, characters) = setup();
+        let trade = trades.find_mut("Alice").unwrap();
+
+        assert!(!trade.accept("Bob"));
+
+        trade.offer_gold(&characters[0], 5).unwrap();
+
+        assert!(!trade.accept("Alice"));
+        assert!(trade.accept("Bob"));
+    }
+
+    #[test]
+    fn completed_trades_swap_everything() {
+        let (mut trades, mut characters) = setup();
+        let trade = trades.find_mut("Alice").unwrap();
+
+        trade.offer_gold(&characters[0], 15).unwrap();
+        trade.offer_item(&characters[0], "Red Potion").unwrap();
+
+        assert!(trade.complete(&mut characters).is_ok());
+        assert_eq!((characters[0].gold, characters[1].gold), (5, 15));
+        assert!(!characters[0].has_item("Red Potion"));
+        assert!(characters[1].has_item("Red Potion"));
+    }
+
+    #[test]
+    fn trades_fall_through_without_changing_anything() {
+        let (mut trades, mut characters) = setup();
+        let trade = trades.find_mut("Alice").unwrap();
+
+        trade.offer_gold(&characters[0], 15).unwrap();
+        trade.offer_item(&characters[0], "Red Potion").unwrap();
+
+        // Alice drank the potion after offering it
+        characters[0].take_item("Red Potion");
+
+        assert!(trade.complete(&mut characters).is_err());
+        assert_eq!((characters[0].gold, characters[1].gold), (20, 0));
+
+        characters[0].inventory.push(item("Red Potion"));
+        characters[1].current_room = 3;
+
+        assert!(trade.complete(&mut characters).is_err());
+
+        characters[1].current_room = 0;
+        characters[1].active = false;
+
+        assert!(trade.complete(&mut characters).is_err());
+    }
+
+    #[test]
+    fn gold_cannot_overflow_the_receiver() {
+        let (mut trades, mut characters) = setup();
+        let trade = trades.find_mut("Alice").unwrap();
+
+        characters[1].gold = u16::MAX - 10;
+        trade.offer_gold(&characters[0], 11).unwrap();
+
+        assert!(trade.complete(&mut characters).is_err());
+        assert_eq!(characters[0].gold, 20);
+    }
+}
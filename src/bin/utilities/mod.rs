@@ -5,7 +5,12 @@ use std::net::TcpStream;
 use serde_json::Value;
 
 use crate::character::Character;
+use crate::effect::is_dark;
+use crate::error_code::ErrorCode;
+use crate::exit::{fog_of_war, visible_exits};
+use crate::item::Item;
 use crate::monster::Monster;
+use crate::npc::Npc;
 
 type Result<T> = result::Result<T, ()>;
 
@@ -39,7 +44,7 @@ pub fn move_character(map: &mut Value, character: &Character, new_room_num: usiz
 }
 
 /// Send all players in a given room a character that just moved into/out of the room
-pub fn alert_room(map: &Value, character: &Character, active_characters: &Vec<Character>, room_num: usize, old_room_num: usize) -> Result<()> {
+pub fn alert_room(map: &Value, character: &Character, active_characters: &[Character], room_num: usize, old_room_num: usize) -> Result<()> {
     let room = &map["rooms"][room_num];
 
     // Get the players in the current room
@@ -79,9 +84,9 @@ pub fn alert_room(map: &Value, character: &Character, active_characters: &Vec<Ch
     Ok(())
 }
 
-/// Send the accept message to the author
-pub fn send_accept(author: &Arc<TcpStream>) -> Result<()> {
-    let message: Vec<u8> = [8,10].to_vec();
+/// Send the accept message for the given message type to the author
+pub fn send_accept(author: &Arc<TcpStream>, accept_type: u8) -> Result<()> {
+    let message: Vec<u8> = [8, accept_type].to_vec();
 
     author.as_ref().write_all(&message).map_err(|err| {
         eprintln!("[UTILS]\t\tError: Could not send accept message to character: {}", err);
@@ -90,6 +95,65 @@ pub fn send_accept(author: &Arc<TcpStream>) -> Result<()> {
     Ok(())
 }
 
+/// Send a MESSAGE to the given connection; narration sets the marker in the sender bytes
+pub fn send_message(author: &Arc<TcpStream>, recipient: &str, sender: &str, text: &str, narration: bool) -> Result<()> {
+    let mut message: Vec<u8> = Vec::new();
+
+    let mut r_bytes = recipient.bytes().collect::<Vec<u8>>();
+    let mut s_bytes = sender.bytes().collect::<Vec<u8>>();
+
+    r_bytes.resize(32, 0);
+    s_bytes.resize(32, 0);
+
+    // Narration marker
+    if narration {
+        s_bytes[30] = 0;
+        s_bytes[31] = 1;
+    }
+
+    message.push(1);
+    message.extend((text.len() as u16).to_le_bytes());
+    message.extend(r_bytes);
+    message.extend(s_bytes);
+    message.extend(text.as_bytes());
+
+    // Send the message to the author
+    author.as_ref().write_all(&message).map_err(|err| {
+        eprintln!("[UTILS]\t\tError: Could not send message to character: {}", err);
+    })?;
+
+    Ok(())
+}
+
+/// Send a narration message from the server to the author
+pub fn send_narration(author: &Arc<TcpStream>, recipient: &str, text: &str) -> Result<()> {
+    send_message(author, recipient, "Narrator", text, true)
+}
+
+/// Send an error message with the given code to the author
+pub fn send_error(author: &Arc<TcpStream>, error: ErrorCode, text: &str) -> Result<()> {
+    let mut message: Vec<u8> = Vec::new();
+
+    message.push(7);
+    message.push(error.into());
+    message.extend((text.len() as u16).to_le_bytes());
+    message.extend(text.as_bytes());
+
+    // Send the error message to the author
+    author.as_ref().write_all(&message).map_err(|err| {
+        eprintln!("[UTILS]\t\tError: Could not send error message to character: {}", err);
+    })?;
+
+    Ok(())
+}
+
+/// Send a narration message to every active character
+pub fn announce(active_characters: &[Character], text: &str) {
+    for character in active_characters.iter().filter(|c| c.active) {
+        let _ = send_narration(&character.conn, &character.name, text);
+    }
+}
+
 /// Send the current character to the author
 pub fn send_character(author: &Arc<TcpStream>, character: &Character) -> Result<()> {
     let mut message: Vec<u8> = Vec::new();
@@ -152,8 +216,114 @@ pub fn send_monster(author: &Arc<TcpStream>, monster: &Monster) -> Result<()> {
     Ok(())
 }
 
+/// Send an NPC to the author as a CHARACTER that is alive but never fights
+pub fn send_npc(author: &Arc<TcpStream>, npc: &Npc) -> Result<()> {
+    let mut message: Vec<u8> = Vec::new();
+
+    let mut name = npc.name.bytes().collect::<Vec<u8>>();
+    let description = npc.description.bytes().collect::<Vec<u8>>();
+    let desc_len = description.len() as u16;
+
+    // Resize the name to 32 bytes
+    name.resize(32, 0);
+
+    message.push(10);
+    message.extend(name);
+    message.push(0xA0); // 10100000 = Alive, Monster, does not join battles
+    message.extend(0u16.to_le_bytes());
+    message.extend(0u16.to_le_bytes());
+    message.extend(0u16.to_le_bytes());
+    message.extend(100i16.to_le_bytes());
+    message.extend(0u16.to_le_bytes());
+    message.extend(npc.current_room.to_le_bytes());
+    message.extend(desc_len.to_le_bytes());
+    message.extend(description);
+
+    // Send the NPC message to the author
+    author.as_ref().write_all(message.as_slice()).map_err(|err| {
+        eprintln!("[UTILS]\t\tError: Could not send NPC message to character: {}", err);
+    })?;
+
+    Ok(())
+}
+
+/// Send every NPC in the room to the author
+pub fn send_room_npcs(author: &Arc<TcpStream>, npcs: &[Npc], room_num: usize) -> Result<()> {
+    for npc in npcs.iter().filter(|n| n.current_room as usize == room_num) {
+        send_npc(author, npc)?;
+    }
+
+    Ok(())
+}
+
+/// Send an ITEM message (type 15, the ITEM extension) describing a carried item to the author.
+///
+/// Only for clients that listed the ITEM extension in a VERSION message of their own. After the type byte come the
+/// 32 byte name, the kind (0=weapon, 1=armor, 2=consumable, 3=key), the flags, attack, defense, regen, health, value,
+/// the description length and the description. Flags: bit 7 (0x80) set while equipped, bit 6 (0x40) set while
+/// carried, bits 0-5 always 0.
+pub fn send_item(author: &Arc<TcpStream>, item: &Item, equipped: bool, carried: bool) -> Result<()> {
+    let mut message: Vec<u8> = Vec::new();
+
+    let mut name = item.name.bytes().collect::<Vec<u8>>();
+    let description = item.description.bytes().collect::<Vec<u8>>();
+    let desc_len = description.len() as u16;
+
+    // Resize the name to 32 bytes
+    name.resize(32, 0);
+
+    let flags: u8 = ((equipped as u8) << 7) | ((carried as u8) << 6);
+
+    message.push(15);
+    message.extend(name);
+    message.push(item.kind as u8);
+    message.push(flags);
+    message.extend(item.attack.to_le_bytes());
+    message.extend(item.defense.to_le_bytes());
+    message.extend(item.regen.to_le_bytes());
+    message.extend(item.health.to_le_bytes());
+    message.extend(item.value.to_le_bytes());
+    message.extend(desc_len.to_le_bytes());
+    message.extend(description);
+
+    // Send the item message to the author
+    author.as_ref().write_all(message.as_slice()).map_err(|err| {
+        eprintln!("[UTILS]\t\tError: Could not send item message to character: {}", err);
+    })?;
+
+    Ok(())
+}
+
+/// Encode extension names as a VERSION extension list; each is a 2 byte length followed by the name
+pub fn encode_extensions(extensions: &[&str]) -> Vec<u8> {
+    let mut list: Vec<u8> = Vec::new();
+
+    for extension in extensions {
+        list.extend((extension.len() as u16).to_le_bytes());
+        list.extend(extension.as_bytes());
+    }
+
+    list
+}
+
+/// Decode a VERSION extension list into the extension names
+pub fn parse_extensions(list: &[u8]) -> Vec<String> {
+    let mut extensions = Vec::new();
+    let mut i = 0;
+
+    while i + 2 <= list.len() {
+        let len = u16::from_le_bytes([list[i], list[i + 1]]) as usize;
+        let end = (i + 2 + len).min(list.len());
+
+        extensions.push(String::from_utf8_lossy(&list[i + 2..end]).to_string());
+        i = end;
+    }
+
+    extensions
+}
+
 /// Send the current room to the author
-pub fn send_room(author: &Arc<TcpStream>, map: &Value, active_characters: &Vec<Character>, active_monsters: &mut Vec<Monster>, room_num: usize, old_room_num: usize) -> Result<()> {
+pub fn send_room(author: &Arc<TcpStream>, map: &Value, active_characters: &[Character], active_monsters: &mut [Monster], room_num: usize, old_room_num: usize) -> Result<()> {
     let room = &map["rooms"][room_num];
 
     let mut message: Vec<u8> = Vec::new();
@@ -205,15 +375,16 @@ pub fn send_room(author: &Arc<TcpStream>, map: &Value, active_characters: &Vec<C
 
     // Alert all players in the room
     println!("[UTILS]\t\tAlerting room of character movement from room {} to room {}.", old_room_num, room_num);
-    let current_character = active_characters.iter().find(|c| Arc::ptr_eq(&c.conn, &author)).unwrap();
+    let current_character = active_characters.iter().find(|c| Arc::ptr_eq(&c.conn, author)).unwrap();
 
     alert_room(map, current_character, active_characters, room_num as usize, old_room_num as usize).map_err(|_err| {
         eprintln!("[UTILS]\t\tError: Could not alert room of character movement");
     })?;
 
-    // Send the players and monsters in the room
+    // Send the players and monsters in the room, unless it's too dark to see them
     let mut players: Vec<&str> = Vec::new();
     let room_num = room_num as usize;
+    let dark = is_dark(map, room_num, current_character);
 
     // Get the players in the room
     let characters = match map["rooms"][room_num]["characters"].as_array() {
@@ -239,40 +410,24 @@ pub fn send_room(author: &Arc<TcpStream>, map: &Value, active_characters: &Vec<C
             }
         };
 
-        send_character(author, &character)?;
+        if dark && !Arc::ptr_eq(&character.conn, author) {
+            continue;
+        }
+
+        send_character(author, character)?;
     }
     
     println!("[UTILS]\t\tSent all players in room.");
 
     // Get the monster objects in the room
-    let mut monsters = Vec::new();
-
-    let enemies = match map["rooms"][room_num]["monsters"].as_array() {
-        Some(enemies) => enemies,
-        None => {
-            eprintln!("[UTILS]\t\tError: Could not get room monsters");
-            author.as_ref().shutdown(std::net::Shutdown::Read).unwrap_or_default();
-            return Err(());
-        }
-    };
+    let monsters = active_monsters.iter().filter(|m| m.current_room as usize == room_num && !dark).collect::<Vec<&Monster>>();
 
     // Log the monsters in the room
-    monsters.extend(enemies.iter().map(|m| m.as_str().unwrap()).collect::<Vec<&str>>());
-
-    println!("[UTILS]\t\tMonsters in room: {:?}", monsters);
+    println!("[UTILS]\t\tMonsters in room: {:?}", monsters.iter().map(|m| m.name.as_str()).collect::<Vec<&str>>());
 
     // Send all monsters in the room to the author
     for monster in monsters {
-        let enemy = match active_monsters.iter().find(|m| m.name == monster) {
-            Some(enemy) => enemy,
-            None => {
-                eprintln!("[UTILS]\t\tError: Could not get monster from map");
-                author.as_ref().shutdown(std::net::Shutdown::Read).unwrap_or_default();
-                return Err(());
-            }
-        };
-
-        send_monster(author, &enemy)?;
+        send_monster(author, monster)?;
     }
     
     println!("[UTILS]\t\tSent all monsters in room.");
@@ -281,18 +436,12 @@ pub fn send_room(author: &Arc<TcpStream>, map: &Value, active_characters: &Vec<C
 }
 
 /// Send the current connections of the given room to the author
-pub fn send_connections(author: &Arc<TcpStream>, map: &Value, room_num: usize) -> Result<()> {
+pub fn send_connections(author: &Arc<TcpStream>, map: &Value, room_num: usize, character: &Character) -> Result<()> {
     let mut rooms = Vec::new();
 
-    let cur_room = &map["rooms"][room_num];
-    let connecting_rooms = match cur_room["exits"].as_array() {
-        Some(exits) => exits.iter().map(|e| e.as_str().unwrap()).collect::<Vec<&str>>(),
-        None => {
-            eprintln!("[UTILS]\t\tError: Could not get room exits");
-            author.as_ref().shutdown(std::net::Shutdown::Read).unwrap_or_default();
-            return Err(());
-        }
-    };
+    // Only the exits this character can see
+    let exits = visible_exits(map, room_num, character);
+    let connecting_rooms = exits.iter().map(|e| e.room.as_str()).collect::<Vec<&str>>();
 
     let map_rooms = match map["rooms"].as_array() {
         Some(rooms) => rooms,
@@ -303,7 +452,7 @@ pub fn send_connections(author: &Arc<TcpStream>, map: &Value, room_num: usize) -
         }
     };
 
-    rooms.extend(find_rooms(&map_rooms, &connecting_rooms));
+    rooms.extend(find_rooms(map_rooms, &connecting_rooms));
 
     // Send the connecting rooms to the author
     for room in rooms {
@@ -326,7 +475,14 @@ pub fn send_connections(author: &Arc<TcpStream>, map: &Value, room_num: usize) -
             }
         }
 
+        // Under fog of war nothing is known about a room until the character has been there
+        let unexplored = fog_of_war(map) && !character.visited(room_num);
+
         match room["description"].as_str() {
+            Some(_) if unexplored => {
+                description = b"???".to_vec();
+                desc_len = 3;
+            },
             Some(desc) => {
                 description = desc.bytes().collect::<Vec<u8>>(); 
                 desc_len = desc.len() as u16;
@@ -355,8 +511,13 @@ pub fn send_connections(author: &Arc<TcpStream>, map: &Value, room_num: usize) -
     Ok(())
 }
 
+/// Get the name of the given room
+pub fn room_name(map: &Value, room_num: usize) -> &str {
+    map["rooms"][room_num]["name"].as_str().unwrap_or("Unknown")
+}
+
 /// Find the rooms that connect to the given room and return their json objects
-pub fn find_rooms(map: &Vec<Value>, connecting_rooms: &Vec<&str>) -> Vec<Value> {
+pub fn find_rooms(map: &[Value], connecting_rooms: &[&str]) -> Vec<Value> {
     let mut rooms = Vec::new();
 
     for room in map {
@@ -371,7 +532,7 @@ pub fn find_rooms(map: &Vec<Value>, connecting_rooms: &Vec<&str>) -> Vec<Value>
 }
 
 // Sends the character to all players in the room
-pub fn send_player_update_to_room(players: &Vec<&str>, character: &Character, active_characters: &Vec<Character>) -> Result<()> {
+pub fn send_player_update_to_room(players: &[&str], character: &Character, active_characters: &[Character]) -> Result<()> {
     // Send all players in the room the character message
     for player in players {
         let receiptient = match active_characters.iter().find(|c| c.name == *player) {
@@ -396,7 +557,7 @@ pub fn send_player_update_to_room(players: &Vec<&str>, character: &Character, ac
 }
 
 /// Sends the monster to all players in the room
-pub fn send_monster_update_to_room(players: &Vec<&str>, monster: &Monster, active_characters: &Vec<Character>) -> Result<()> {
+pub fn send_monster_update_to_room(players: &[&str], monster: &Monster, active_characters: &[Character]) -> Result<()> {
     // Send all players in the room the monster message
     for player in players {
         let receiptient = match active_characters.iter().find(|c| c.name == *player) {
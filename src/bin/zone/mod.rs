@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::result;
+use serde_json::Value;
+
+use crate::schema::{find_map, read_map};
+
+/// Why the zones could not be loaded, for the caller to report
+type Result<T> = result::Result<T, String>;
+
+/// Lists in the map that are merged across zones, and the field that keeps their entries apart
+const MERGED_LISTS: [(&str, Option<&str>); 7] = [
+    ("monsters", None),
+    ("items", Some("name")),
+    ("shops", None),
+    ("bosses", Some("monster")),
+    ("events", None),
+    ("npcs", Some("name")),
+    ("quests", Some("id")),
+];
+
+/// One map file loaded into the world, owning a range of room numbers
+#[derive(Debug, Clone)]
+pub struct Zone {
+    pub map_num: u8,
+    pub name: String,
+    pub first_room: u16,
+    pub rooms: u16,
+}
+
+impl Zone {
+    pub fn contains(&self, room_num: u16) -> bool {
+        room_num >= self.first_room && room_num < self.first_room + self.rooms
+    }
+}
+
+/// Load every map as a zone of a single world.
+///
+/// Each zone's rooms are numbered after the rooms of the zones before it so room numbers stay unique,
+/// and every room number in the zone's rooms, monsters, shops, events, NPCs and quests is moved along with them.
+/// Game rules such as combat and death come from the first map. Room names must be unique across zones,
+/// and an exit object with a "zone" leads to a room in another zone.
+pub fn load_zones(map_path: &str, map_nums: &[u8]) -> Result<Value> {
+    let mut world: Option<Value> = None;
+    let mut zones: Vec<Zone> = Vec::new();
+
+    for map_num in map_nums {
+        let path = find_map(map_path, *map_num).ok_or_else(|| format!("There is no map file for map {} in {}", map_num, map_path))?;
+
+        let mut map = read_map(&path)?;
+
+        let first_room = zones.iter().map(|z| z.rooms as usize).sum::<usize>();
+        let rooms = map["rooms"].as_array().map(|r| r.len()).unwrap_or(0);
+
+        if first_room + rooms > u16::MAX as usize {
+            return Err(format!("Map {} has too many rooms to fit in the world", map_num));
+        }
+
+        let zone = Zone {
+            map_num: *map_num,
+            name: map["name"].as_str().map(|n| n.to_string()).unwrap_or(format!("Zone {}", map_num)),
+            first_room: first_room as u16,
+            rooms: rooms as u16,
+        };
+
+        if zones.iter().any(|z| z.map_num == zone.map_num) {
+            return Err(format!("Map {} is loaded more than once", map_num));
+        }
+
+        renumber(&mut map, zone.first_room);
+
+        println!("[ZONE]\t\tLoaded {} ({} rooms from {})", zone.name, zone.rooms, zone.first_room);
+
+        world = Some(match world {
+            None => map,
+            Some(mut world) => {
+                merge(&mut world, map, &zone)?;
+                world
+            }
+        });
+
+        zones.push(zone);
+    }
+
+    let mut world = world.ok_or_else(|| String::from("No maps to load"))?;
+
+    link_portals(&mut world, &zones);
+
+    world["zones"] = Value::Array(zones.iter().map(|z| {
+        serde_json::json!({ "map": z.map_num, "name": z.name, "first_room": z.first_room, "rooms": z.rooms })
+    }).collect());
+
+    Ok(world)
+}
+
+/// Load the same maps again, for reloading the world while the server runs
+pub fn reload_zones(map_path: &str, map: &Value) -> Result<Value> {
+    let map_nums = zones(map).iter().map(|z| z.map_num).collect::<Vec<u8>>();
+
+    load_zones(map_path, &map_nums)
+}
+
+/// The zones loaded into the world, from the map
+pub fn zones(map: &Value) -> Vec<Zone> {
+    match map["zones"].as_array() {
+        Some(zones) => zones.iter().filter_map(|z| Some(Zone {
+            map_num: z["map"].as_u64()? as u8,
+            name: z["name"].as_str()?.to_string(),
+            first_room: z["first_room"].as_u64()? as u16,
+            rooms: z["rooms"].as_u64()? as u16,
+        })).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The zone a room belongs to
+pub fn zone_of(map: &Value, room_num: u16) -> Option<Zone> {
+    zones(map).into_iter().find(|z| z.contains(room_num))
+}
+
+/// A room's name, with its zone when the world has more than one
+pub fn place_name(map: &Value, room_num: u16) -> String {
+    let room = map["rooms"][room_num as usize]["name"].as_str().unwrap_or("Unknown");
+
+    match zone_of(map, room_num) {
+        Some(zone) if zones(map).len() > 1 => format!("{} ({})", room, zone.name),
+        _ => room.to_string(),
+    }
+}
+
+/// Add an offset to a room number stored in the map under the key, if there is one
+fn shift(entry: &mut Value, key: &str, offset: u16) {
+    if let Some(value) = entry.get_mut(key) {
+        if let Some(room) = value.as_u64() {
+            *value = Value::from(room + offset as u64);
+        }
+    }
+}
+
+/// The entries of a list in the map, or none if it isn't there
+fn entries<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+    value.get_mut(key).and_then(|list| list.as_array_mut()).into_iter().flatten()
+}
+
+/// Move every room number in a map along by the offset
+fn renumber(map: &mut Value, offset: u16) {
+    if offset == 0 {
+        return;
+    }
+
+    for room in entries(map, "rooms") {
+        shift(room, "id", offset);
+
+        for effect in entries(room, "effects") {
+            shift(effect, "room", offset);
+        }
+    }
+
+    for monster in entries(map, "monsters") {
+        shift(monster, "current_room", offset);
+    }
+
+    for shop in entries(map, "shops") {
+        shift(shop, "room", offset);
+    }
+
+    for event in entries(map, "events") {
+        shift(event, "room", offset);
+
+        for room in entries(event, "rooms") {
+            shift(room, "room", offset);
+        }
+    }
+
+    for npc in entries(map, "npcs") {
+        shift(npc, "current_room", offset);
+    }
+
+    for quest in entries(map, "quests") {
+        for objective in entries(quest, "objectives") {
+            shift(objective, "room", offset);
+        }
+    }
+}
+
+/// Add a zone's rooms and lists to the world
+fn merge(world: &mut Value, mut map: Value, zone: &Zone) -> Result<()> {
+    let rooms = entries(&mut map, "rooms").map(std::mem::take).collect::<Vec<Value>>();
+
+    for room in rooms {
+        let name = room["name"].as_str().unwrap_or("");
+
+        if world["rooms"].as_array().into_iter().flatten().any(|r| r["name"].as_str() == Some(name)) {
+            return Err(format!("Room name '{}' in {} is already used by another zone", name, zone.name));
+        }
+
+        if let Some(world_rooms) = world["rooms"].as_array_mut() {
+            world_rooms.push(room);
+        }
+    }
+
+    for (list, key) in MERGED_LISTS {
+        let additions = entries(&mut map, list).map(std::mem::take).collect::<Vec<Value>>();
+
+        if additions.is_empty() {
+            continue;
+        }
+
+        if !world[list].is_array() {
+            world[list] = Value::Array(Vec::new());
+        }
+
+        let world_list = world[list].as_array_mut().unwrap();
+
+        for entry in additions {
+            // Zones can share items and quests, the first zone to define one wins
+            if let Some(key) = key {
+                if world_list.iter().any(|e| e[key] == entry[key]) {
+                    println!("[ZONE]\t\t{} already has {} '{}', keeping the first", list, key, entry[key]);
+                    continue;
+                }
+            }
+
+            world_list.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check every exit that leads into another zone, dropping portals to zones or rooms that aren't loaded
+fn link_portals(world: &mut Value, zones: &[Zone]) {
+    let names = world["rooms"].as_array().into_iter().flatten()
+        .map(|r| (r["name"].as_str().unwrap_or("").to_string(), r["id"].as_u64().unwrap_or(0) as u16))
+        .collect::<HashMap<String, u16>>();
+
+    for room in entries(world, "rooms") {
+        let from = room["name"].as_str().unwrap_or("").to_string();
+
+        let exits = match room.get_mut("exits").and_then(|e| e.as_array_mut()) {
+            Some(exits) => exits,
+            None => continue,
+        };
+
+        exits.retain_mut(|exit| {
+            let map_num = match exit["zone"].as_u64() {
+                Some(map_num) => map_num as u8,
+                None => return true,
+            };
+
+            let zone = match zones.iter().find(|z| z.map_num == map_num) {
+                Some(zone) => zone,
+                None => {
+                    println!("[ZONE]\t\tPortal from {} leads to map {} which isn't loaded, leaving it out", from, map_num);
+                    return false;
+                }
+            };
+
+            match exit["room"].as_str().and_then(|name| names.get(name)) {
+                Some(id) if zone.contains(*id) => {
+                    exit["portal"] = Value::String(zone.name.clone());
+                    true
+                },
+                _ => {
+                    eprintln!("[ZONE]\t\tError: Portal from {} leads to {} which isn't in {}", from, exit["room"], zone.name);
+                    false
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn forest() -> Value {
+        json!({
+            "name": "Forest",
+            "rooms": [
+                { "id": 0, "name": "Glade", "exits": ["Thicket", { "zone": 7, "room": "Gate" }] },
+                { "id": 1, "name": "Thicket", "exits": ["Glade", { "zone": 9, "room": "Nowhere" }] },
+            ],
+            "monsters": [{ "name": "Wolf", "current_room": 1 }],
+            "items": [{ "name": "Stick" }],
+        })
+    }
+
+    fn castle() -> Value {
+        json!({
+            "name": "Castle",
+            "rooms": [
+                { "id": 0, "name": "Gate", "exits": ["Hall", { "zone": 6, "room": "Glade" }, { "zone": 6, "room": "Hall" }], "effects": [{ "room": 1 }] },
+                { "id": 1, "name": "Hall", "exits": ["Gate"] },
+            ],
+            "monsters": [{ "name": "Guard", "current_room": 0 }],
+            "items": [{ "name": "Stick", "value": 5 }, { "name": "Crown" }],
+            "shops": [{ "room": 1 }],
+            "events": [{ "room": 0, "rooms": [{ "room": 1 }] }],
+            "npcs": [{ "name": "King", "current_room": 1 }],
+            "quests": [{ "id": "crown", "objectives": [{ "room": 0 }] }],
+        })
+    }
+
+    #[test]
+    fn renumber_moves_every_room_number() {
+        let mut map = castle();
+        renumber(&mut map, 2);
+
+        assert_eq!(map["rooms"][0]["id"], 2);
+        assert_eq!(map["rooms"][1]["id"], 3);
+        assert_eq!(map["rooms"][0]["effects"][0]["room"], 3);
+        assert_eq!(map["monsters"][0]["current_room"], 2);
+        assert_eq!(map["shops"][0]["room"], 3);
+        assert_eq!(map["events"][0]["room"], 2);
+        assert_eq!(map["events"][0]["rooms"][0]["room"], 3);
+        assert_eq!(map["npcs"][0]["current_room"], 3);
+        assert_eq!(map["quests"][0]["objectives"][0]["room"], 2);
+    }
+
+    #[test]
+    fn merge_keeps_the_first_of_shared_entries() {
+        let mut world = forest();
+        let zone = Zone { map_num: 7, name: String::from("Castle"), first_room: 2, rooms: 2 };
+
+        merge(&mut world, castle(), &zone).unwrap();
+
+        assert_eq!(world["rooms"].as_array().unwrap().len(), 4);
+        assert_eq!(world["monsters"].as_array().unwrap().len(), 2);
+        assert_eq!(world["items"], json!([{ "name": "Stick" }, { "name": "Crown" }]));
+        assert_eq!(world["shops"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn merge_rejects_repeated_room_names() {
+        let mut world = forest();
+        let zone = Zone { map_num: 8, name: String::from("Forest Again"), first_room: 2, rooms: 2 };
+
+        assert!(merge(&mut world, forest(), &zone).is_err());
+    }
+
+    #[test]
+    fn link_portals_drops_portals_that_lead_nowhere() {
+        let zones = [
+            Zone { map_num: 6, name: String::from("Forest"), first_room: 0, rooms: 2 },
+            Zone { map_num: 7, name: String::from("Castle"), first_room: 2, rooms: 2 },
+        ];
+
+        let mut world = forest();
+        let mut castle = castle();
+        renumber(&mut castle, 2);
+        merge(&mut world, castle, &zones[1]).unwrap();
+
+        link_portals(&mut world, &zones);
+
+        // Into a zone that is loaded
+        assert_eq!(world["rooms"][0]["exits"], json!(["Thicket", { "zone": 7, "room": "Gate", "portal": "Castle" }]));
+        // Into a zone that isn't loaded
+        assert_eq!(world["rooms"][1]["exits"], json!(["Glade"]));
+        // Into a room that is in a different zone than the portal says
+        assert_eq!(world["rooms"][2]["exits"], json!(["Hall", { "zone": 6, "room": "Glade", "portal": "Forest" }]));
+    }
+
+    #[test]
+    fn load_zones_numbers_zones_in_order() {
+        let map_path = std::env::temp_dir().join(format!("lurk-zone-{}-map", std::process::id())).to_string_lossy().to_string();
+        std::fs::write(format!("{}6.json", map_path), forest().to_string()).unwrap();
+        std::fs::write(format!("{}7.json", map_path), castle().to_string()).unwrap();
+
+        let world = load_zones(&map_path, &[6, 7]);
+        let repeated = load_zones(&map_path, &[6, 6]);
+        let missing = load_zones(&map_path, &[6, 5]);
+
+        std::fs::remove_file(format!("{}6.json", map_path)).unwrap();
+        std::fs::remove_file(format!("{}7.json", map_path)).unwrap();
+
+        let world = world.unwrap();
+        let zones = zones(&world);
+
+        assert_eq!(zones.len(), 2);
+        assert_eq!((zones[1].map_num, zones[1].first_room, zones[1].rooms), (7, 2, 2));
+        assert_eq!(zone_of(&world, 3).unwrap().name, "Castle");
+        assert_eq!(place_name(&world, 1), "Thicket (Forest)");
+        assert_eq!(world["npcs"][0]["current_room"], 3);
+        assert!(repeated.is_err());
+        assert!(missing.is_err());
+    }
+}